@@ -0,0 +1,97 @@
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+use crate::cartridge::CartridgeError;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+
+/// a rom read off the disk, taken out of an archive if it was in one
+pub struct RomFile {
+    pub data: Vec<u8>,
+    /// where the rom would be if it wasnt in an archive, the
+    /// save and patch files get named after this
+    pub base_path: PathBuf,
+}
+
+/// reads a rom which can be a plain file, gzipped, or in a zip. A rom in a
+/// zip with several in it gets picked with `archive.zip:game.gb`
+pub fn read_rom_file(rom_path: &String) -> Result<RomFile, CartridgeError> {
+    let (path, entry) = split_selector(rom_path);
+    let data = std::fs::read(&path)?;
+
+    if data.starts_with(ZIP_MAGIC) {
+        return read_zip(&path, data, entry);
+    }
+    if entry.is_some() {
+        return Err(CartridgeError::Archive(format!("{} is not a zip archive", path.display())));
+    }
+    if data.starts_with(GZIP_MAGIC) {
+        let mut rom = Vec::new();
+        GzDecoder::new(&data[..]).read_to_end(&mut rom)
+            .map_err(|e| CartridgeError::Archive(format!("could not decompress {}: {e}", path.display())))?;
+        // game.gb.gz is just game.gb once it has been decompressed
+        return Ok(RomFile { data: rom, base_path: path.with_extension("") });
+    }
+    Ok(RomFile { data, base_path: path })
+}
+
+/// only treats what is after the last colon as an entry when the file exists
+/// without it, so paths with colons in them still work
+fn split_selector(rom_path: &String) -> (PathBuf, Option<String>) {
+    if Path::new(rom_path).exists() {
+        return (PathBuf::from(rom_path), None);
+    }
+    match rom_path.rsplit_once(':') {
+        Some((path, entry)) if Path::new(path).is_file() => (PathBuf::from(path), Some(entry.to_string())),
+        _ => (PathBuf::from(rom_path), None),
+    }
+}
+
+fn read_zip(path: &Path, data: Vec<u8>, entry: Option<String>) -> Result<RomFile, CartridgeError> {
+    let archive_error = |e: zip::result::ZipError| CartridgeError::Archive(format!("{}: {e}", path.display()));
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(archive_error)?;
+
+    let files: Vec<String> = archive.file_names()
+        .filter(|name| !name.ends_with('/'))
+        .map(String::from)
+        .collect();
+    let roms: Vec<&String> = files.iter().filter(|name| is_rom_name(name)).collect();
+
+    let name = match entry {
+        Some(entry) => files.iter()
+            .find(|name| **name == entry || file_name(name) == entry)
+            .ok_or_else(|| CartridgeError::Archive(format!("{entry} is not in {}", path.display())))?
+            .clone(),
+        None if roms.len() == 1 => roms[0].clone(),
+        None if roms.is_empty() && files.len() == 1 => files[0].clone(),
+        None if roms.is_empty() => {
+            return Err(CartridgeError::Archive(format!("there is no .gb or .gbc file in {}", path.display())));
+        }
+        None => {
+            let names: Vec<&str> = roms.iter().map(|name| name.as_str()).collect();
+            return Err(CartridgeError::Archive(format!(
+                "{} has several roms in it, pick one with {}:<rom>, it has {}",
+                path.display(), path.display(), names.join(", "),
+            )));
+        }
+    };
+
+    let mut rom = Vec::new();
+    archive.by_name(&name).map_err(archive_error)?
+        .read_to_end(&mut rom)?;
+    // the rom gets treated like it was sitting next to the archive
+    let base_path = path.with_file_name(file_name(&name));
+    Ok(RomFile { data: rom, base_path })
+}
+
+fn is_rom_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.ends_with(".gb") || name.ends_with(".gbc")
+}
+
+/// zip entries can be inside folders
+fn file_name(name: &str) -> &str {
+    name.rsplit('/').next().unwrap_or(name)
+}
@@ -1,12 +1,58 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use crate::model::Model;
+
+/// what the ram should contain when the gameboy is switched on
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum RamInit {
+    Zero,
+    Random,
+    Pattern,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// prints the cartridge header of a rom without running it
+    Info { rom_name: String },
+}
 
 #[derive(Parser, Debug)]
 #[clap(author, version, allow_hyphen_values(true))]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
-    pub rom_name: String,
+    #[command(subcommand)]
+    pub command: Option<Command>,
 
-    #[arg(short, long)]
+    #[arg(required = true)]
+    pub rom_name: Option<String>,
+
+    /// starts in the state the boot rom would leave it in, which is
+    /// what happens anyway without --boot-rom. Kept for old command lines
+    #[arg(short, long, conflicts_with = "boot_rom")]
     pub booted: bool,
+    /// a boot rom to run before the game, without one the
+    /// emulator starts in the state the boot rom would leave it in
+    #[arg(long)]
+    pub boot_rom: Option<String>,
+    /// an ips, bps or ups patch to apply to the rom. Without this one
+    /// next to the rom with the same name gets used
+    #[arg(long)]
+    pub patch: Option<String>,
+    /// loads battery backed ram from a .sav file next to the rom and writes it back
     #[arg(short, long)]
-    pub save: bool,  
+    pub save: bool,
+
+    /// the hardware being emulated
+    #[arg(long, value_enum, default_value_t = Model::Dmg)]
+    pub model: Model,
+
+    /// the contents of wram, hram, vram and cartridge ram at power on
+    #[arg(long, value_enum, default_value_t = RamInit::Zero)]
+    pub ram_init: RamInit,
+    /// the seed used for random ram, a random one is picked if not given
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// png images the pocket camera sees, repeat it to go through several
+    #[arg(long)]
+    pub camera: Vec<String>,
 }
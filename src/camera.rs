@@ -0,0 +1,256 @@
+use macroquad::texture::Image;
+use crate::mbc::{banked_ram_address, load_into, read_banked_rom, rom_bank_number, MBC};
+
+/// the part of the sensor the camera actually uses
+pub const SENSOR_WIDTH: usize = 128;
+pub const SENSOR_HEIGHT: usize = 112;
+
+/// how many frames each picture is in front of the camera for, when there are several
+pub const FRAMES_PER_PICTURE: u64 = 60;
+
+/// where the captured picture goes in the first ram bank, as 16x14 tiles
+const CAPTURE_ADDRESS: usize = 0x100;
+/// the registers are 0xA000-0xA035, the rest of the block is unused
+const CAMERA_REGISTERS: usize = 0x36;
+
+/// how much the edge enhancement ratio in A004 boosts edges by
+const EDGE_RATIOS: [f32; 8] = [0.5, 0.75, 1.0, 1.25, 2.0, 3.0, 4.0, 5.0];
+
+/// the mapper in the pocket camera, mostly an mbc3 without the clock apart
+/// from the sensor registers that get mapped in place of the ram.
+/// the registers are from https://gbdev.io/pandocs/Gameboy_Camera.html
+pub struct PocketCamera {
+    rom: Vec<u8>,
+    rom_index: usize,
+    total_rom_banks: usize,
+
+    ram: Vec<u8>,
+    ram_index: usize,
+    ram_enabled: bool,
+    // bit 4 of the ram bank swaps the ram for the registers
+    registers_mapped: bool,
+
+    registers: [u8; CAMERA_REGISTERS],
+    // how many cycles until the picture being taken is done
+    capture_cycles: Option<usize>,
+    // a greyscale image of what the sensor is pointed at, 0 is black
+    frame: Vec<u8>,
+}
+impl PocketCamera {
+    pub fn new(rom: Vec<u8>, total_rom_banks: usize, ram: Vec<u8>) -> Self {
+        Self {
+            rom,
+            rom_index: 1,
+            total_rom_banks,
+            ram,
+            ram_index: 0,
+            ram_enabled: false,
+            registers_mapped: false,
+            registers: [0; CAMERA_REGISTERS],
+            capture_cycles: None,
+            // an evenly lit grey wall until the frontend gives it something to look at
+            frame: vec![0x80; SENSOR_WIDTH * SENSOR_HEIGHT],
+        }
+    }
+
+    fn ram_address(&self, address: usize) -> usize {
+        banked_ram_address(&self.ram, self.ram_index, address)
+    }
+
+    fn exposure(&self) -> usize {
+        (self.registers[2] as usize) << 8 | self.registers[3] as usize
+    }
+
+    /// the capture time is counted in the 1MiHz the sensor is clocked at
+    fn start_capture(&mut self) {
+        let n_bit = if self.registers[1] & 0x80 != 0 { 0 } else { 512 };
+        let cycles = 32446 + n_bit + 16 * self.exposure();
+        self.capture_cycles = Some(cycles * 4);
+    }
+
+    /// runs the frame through the same steps the sensor and the mapper do:
+    /// exposure and gain, inverting, edge enhancement and then dithering down
+    /// to 4 shades. The zero point, offset and reference voltage only move the
+    /// analog signal around so they arent emulated
+    fn capture(&mut self) {
+        let gain = (self.registers[1] & 0x1F) as f32;
+        // every step of gain is roughly another 1.5dB
+        let gain = 10_f32.powf(gain * 1.5 / 20.0);
+        let brightness = self.exposure() as f32 / 0x1000 as f32 * gain;
+        let invert = self.registers[4] & 0x08 != 0;
+
+        let mut signal: Vec<f32> = self.frame.iter()
+            .map(|pixel| {
+                let value = (*pixel as f32 * brightness).min(255.0);
+                if invert { 255.0 - value } else { value }
+            })
+            .collect();
+
+        let edges = (self.registers[1] >> 5) & 0b11;
+        if edges != 0 {
+            let ratio = EDGE_RATIOS[(self.registers[4] as usize >> 4) & 0x07];
+            let at = |x: usize, y: usize| signal[y * SENSOR_WIDTH + x];
+            let mut enhanced = signal.clone();
+            for y in 0..SENSOR_HEIGHT {
+                for x in 0..SENSOR_WIDTH {
+                    let value = at(x, y);
+                    let mut edge = 0.0;
+                    if edges & 0b01 != 0 {
+                        let left = at(x.saturating_sub(1), y);
+                        let right = at((x + 1).min(SENSOR_WIDTH - 1), y);
+                        edge += 2.0 * value - left - right;
+                    }
+                    if edges & 0b10 != 0 {
+                        let up = at(x, y.saturating_sub(1));
+                        let down = at(x, (y + 1).min(SENSOR_HEIGHT - 1));
+                        edge += 2.0 * value - up - down;
+                    }
+                    enhanced[y * SENSOR_WIDTH + x] = (value + ratio * edge).clamp(0.0, 255.0);
+                }
+            }
+            signal = enhanced;
+        }
+
+        for y in 0..SENSOR_HEIGHT {
+            for x in 0..SENSOR_WIDTH {
+                // every pixel in the 4x4 matrix gets 3 thresholds
+                let matrix = 6 + ((y % 4) * 4 + (x % 4)) * 3;
+                let thresholds = &self.registers[matrix..matrix + 3];
+                let value = signal[y * SENSOR_WIDTH + x] as u8;
+                let colour = match value {
+                    _ if value < thresholds[0] => 3,
+                    _ if value < thresholds[1] => 2,
+                    _ if value < thresholds[2] => 1,
+                    _ => 0,
+                };
+
+                let tile = (y / 8) * (SENSOR_WIDTH / 8) + x / 8;
+                let address = CAPTURE_ADDRESS + tile * 16 + (y % 8) * 2;
+                let bit = 7 - (x % 8);
+                self.ram[address] = (self.ram[address] & !(1 << bit)) | (colour & 1) << bit;
+                self.ram[address + 1] = (self.ram[address + 1] & !(1 << bit)) | (colour >> 1) << bit;
+            }
+        }
+    }
+}
+impl MBC for PocketCamera {
+    fn read_rom(&self, address: usize) -> u8 {
+        read_banked_rom(&self.rom, self.rom_index % self.total_rom_banks, address)
+    }
+    fn write_rom(&mut self, address: usize, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = data & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_index = rom_bank_number(data, 0x3F),
+            0x4000..=0x5FFF => {
+                self.registers_mapped = data & 0x10 != 0;
+                if !self.registers_mapped {
+                    self.ram_index = data as usize & 0x0F;
+                }
+            }
+            _ => {}
+        }
+    }
+    /// the ram can be read even when it isnt enabled
+    fn read_ram(&self, address: usize) -> u8 {
+        if !self.registers_mapped {
+            return self.ram[self.ram_address(address)];
+        }
+        // only the first register can be read, everything else is 0
+        match address & 0x7F {
+            0x00 => (self.registers[0] & 0x06) | self.capture_cycles.is_some() as u8,
+            _ => 0x00,
+        }
+    }
+    fn write_ram(&mut self, address: usize, data: u8) {
+        if !self.registers_mapped {
+            if self.ram_enabled {
+                let ram_address = self.ram_address(address);
+                self.ram[ram_address] = data;
+            }
+            return;
+        }
+        let register = address & 0x7F;
+        if register >= CAMERA_REGISTERS {
+            return;
+        }
+        if register == 0x00 {
+            if data & 1 == 1 && self.capture_cycles.is_none() {
+                self.start_capture();
+            }
+            // writing a 0 cancels the capture
+            if data & 1 == 0 {
+                self.capture_cycles = None;
+            }
+            self.registers[0] = data & 0x06;
+            return;
+        }
+        self.registers[register] = data;
+    }
+    fn tick(&mut self, cycles: u8) {
+        let Some(remaining) = self.capture_cycles else {
+            return;
+        };
+        match remaining.checked_sub(cycles as usize) {
+            Some(remaining) if remaining > 0 => self.capture_cycles = Some(remaining),
+            _ => {
+                self.capture_cycles = None;
+                self.capture();
+            }
+        }
+    }
+    fn save_ram(&self) -> Option<Vec<u8>> {
+        Some(self.ram.clone())
+    }
+    fn load_ram(&mut self, data: &[u8]) {
+        load_into(&mut self.ram, data);
+    }
+    fn set_camera_frame(&mut self, frame: &[u8]) {
+        load_into(&mut self.frame, frame);
+    }
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+}
+
+/// loads a png as a greyscale picture of what the sensor can see, scaling it
+/// so it fills the sensor and cutting off whatever doesnt fit
+pub fn load_camera_frame(path: &String) -> Result<Vec<u8>, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("could not read {path}: {e}"))?;
+    let image = Image::from_file_with_format(&bytes, None)
+        .map_err(|e| format!("could not decode {path}: {e}"))?;
+
+    let (width, height) = (image.width as f32, image.height as f32);
+    let scale = (width / SENSOR_WIDTH as f32).min(height / SENSOR_HEIGHT as f32);
+    let x_offset = (width - SENSOR_WIDTH as f32 * scale) / 2.0;
+    let y_offset = (height - SENSOR_HEIGHT as f32 * scale) / 2.0;
+
+    let mut frame = Vec::with_capacity(SENSOR_WIDTH * SENSOR_HEIGHT);
+    for y in 0..SENSOR_HEIGHT {
+        for x in 0..SENSOR_WIDTH {
+            let image_x = (x_offset + x as f32 * scale) as u32;
+            let image_y = (y_offset + y as f32 * scale) as u32;
+            let colour = image.get_pixel(image_x, image_y);
+            let luma = 0.299 * colour.r + 0.587 * colour.g + 0.114 * colour.b;
+            frame.push((luma * 255.0) as u8);
+        }
+    }
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rom_bank_0_maps_bank_1() {
+        let mut rom = vec![0; 4 * 0x4000];
+        for bank in 0..4 {
+            rom[bank * 0x4000] = bank as u8;
+        }
+        let mut camera = PocketCamera::new(rom, 4, vec![0; 0x20000]);
+        camera.write_rom(0x2000, 0x00);
+        assert_eq!(camera.read_rom(0x4000), 1);
+        camera.write_rom(0x2000, 0x03);
+        assert_eq!(camera.read_rom(0x4000), 3);
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt;
+use std::path::PathBuf;
+use crate::archive::{read_rom_file, RomFile};
+use crate::patch::{apply_patch, PATCH_EXTENSIONS};
+use crate::util::NINTENDO_LOGO;
+
+/// everything that can stop a rom from being loaded
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(std::io::Error),
+    // not even big enough to have a header
+    NoHeader(usize),
+    UnsupportedMbc(u8),
+    UnsupportedRomSize(u8),
+    UnsupportedRamSize(u8),
+    Truncated { file: usize, header: usize },
+    Patch(String),
+    Archive(String),
+}
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read the rom: {e}"),
+            Self::NoHeader(len) => write!(f, "file is {} which is too small to have a header", format_size(*len)),
+            Self::UnsupportedMbc(code) => write!(f, "MBC type 0x{code:02X} not supported"),
+            Self::UnsupportedRomSize(code) => write!(f, "rom size 0x{code:02X} not supported"),
+            Self::UnsupportedRamSize(code) => write!(f, "ram size 0x{code:02X} not supported"),
+            Self::Truncated { file, header } => {
+                write!(f, "file is {} but header claims {}", format_size(*file), format_size(*header))
+            }
+            Self::Patch(e) => write!(f, "could not apply the patch: {e}"),
+            Self::Archive(e) => write!(f, "{e}"),
+        }
+    }
+}
+impl std::error::Error for CartridgeError {}
+impl From<std::io::Error> for CartridgeError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+fn format_size(bytes: usize) -> String {
+    match bytes {
+        _ if bytes >= 0x100000 && bytes.is_multiple_of(0x100000) => format!("{} MiB", bytes / 0x100000),
+        _ if bytes >= 0x400 && bytes.is_multiple_of(0x400) => format!("{} KiB", bytes / 0x400),
+        _ => format!("{bytes} bytes"),
+    }
+}
+
+/// reads the rom, out of an archive if needs be, making sure there is at least a header to look at. A patch
+/// gets applied if one is given or there is one next to the rom with the same name
+pub fn get_rom(rom_path: &String, patch_path: Option<&String>) -> Result<RomFile, CartridgeError> {
+    let RomFile { data: mut rom, base_path } = read_rom_file(rom_path)?;
+
+    let patch_path = patch_path.map(PathBuf::from).or_else(|| {
+        PATCH_EXTENSIONS.iter()
+            .map(|extension| base_path.with_extension(extension))
+            .find(|path| path.exists())
+    });
+    if let Some(patch_path) = patch_path {
+        println!("applying the patch {}", patch_path.display());
+        let patch = std::fs::read(&patch_path)
+            .map_err(|e| CartridgeError::Patch(format!("could not read {}: {e}", patch_path.display())))?;
+        rom = apply_patch(&rom, &patch).map_err(CartridgeError::Patch)?;
+    }
+
+    if rom.len() < 0x150 {
+        return Err(CartridgeError::NoHeader(rom.len()));
+    }
+    Ok(RomFile { data: rom, base_path })
+}
+
+/// everything in the cartridge header from 0x100 to 0x14F.
+/// the layout is from https://gbdev.io/pandocs/The_Cartridge_Header.html
+#[derive(Debug, Clone)]
+pub struct Header {
+    pub logo: [u8; 48],
+    pub title: String,
+    // only newer cartridges have one, older ones used these bytes for the title
+    pub manufacturer_code: Option<String>,
+    pub cgb_flag: u8,
+    pub sgb_flag: u8,
+    pub licensee: String,
+    pub mbc_type: u8,
+    pub rom_size_code: u8,
+    pub ram_size_code: u8,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+impl Header {
+    pub fn new(rom: &[u8]) -> Self {
+        let cgb_flag = rom[0x143];
+        let manufacturer = &rom[0x13F..0x143];
+        let has_manufacturer = cgb_flag & 0x80 != 0
+            && manufacturer.iter().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+
+        // the title gets shorter every time something new was added to the header
+        let title_end = match (has_manufacturer, cgb_flag & 0x80 != 0) {
+            (true, _) => 0x13F,
+            (false, true) => 0x143,
+            (false, false) => 0x144,
+        };
+        let title = rom[0x134..title_end].iter()
+            .take_while(|c| **c != 0)
+            .map(|c| *c as char)
+            .collect();
+
+        // 0x33 means the licensee is in the two newer bytes instead
+        let licensee = match rom[0x14B] {
+            0x33 => String::from_utf8_lossy(&rom[0x144..0x146]).into_owned(),
+            old => format!("{old:02X}"),
+        };
+
+        Self {
+            logo: rom[0x104..0x134].try_into().unwrap(),
+            title,
+            manufacturer_code: has_manufacturer.then(|| String::from_utf8_lossy(manufacturer).into_owned()),
+            cgb_flag,
+            sgb_flag: rom[0x146],
+            licensee,
+            mbc_type: rom[0x147],
+            rom_size_code: rom[0x148],
+            ram_size_code: rom[0x149],
+            version: rom[0x14C],
+            header_checksum: rom[0x14D],
+            global_checksum: (rom[0x14E] as u16) << 8 | rom[0x14F] as u16,
+        }
+    }
+
+    pub fn has_valid_logo(&self) -> bool {
+        self.logo == NINTENDO_LOGO
+    }
+
+    /// how many 16KiB banks the header says the rom has
+    pub fn rom_banks(&self) -> Result<usize, CartridgeError> {
+        match self.rom_size_code {
+            0x00..=0x08 => Ok(2 << self.rom_size_code),
+            0x52 => Ok(72),
+            0x53 => Ok(80),
+            0x54 => Ok(96),
+            code => Err(CartridgeError::UnsupportedRomSize(code)),
+        }
+    }
+
+    /// how many bytes of ram the header says the cartridge has
+    pub fn ram_size(&self) -> Result<usize, CartridgeError> {
+        match self.ram_size_code {
+            0x00 => Ok(0),
+            // unofficial, but a few homebrew roms use it
+            0x01 => Ok(0x800),
+            0x02 => Ok(0x2000),
+            0x03 => Ok(0x8000),
+            0x04 => Ok(0x20000),
+            0x05 => Ok(0x10000),
+            code => Err(CartridgeError::UnsupportedRamSize(code)),
+        }
+    }
+}
+
+/// the checksum the boot rom checks, the game wont start if this is wrong
+pub fn header_checksum(rom: &[u8]) -> u8 {
+    rom[0x134..=0x14C].iter().fold(0_u8, |sum, byte| sum.wrapping_sub(*byte).wrapping_sub(1))
+}
+
+/// every byte of the rom added up apart from the checksum itself.
+/// nothing actually checks this one
+pub fn global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|(i, _)| *i != 0x14E && *i != 0x14F)
+        .fold(0_u16, |sum, (_, byte)| sum.wrapping_add(*byte as u16))
+}
+
+fn mbc_name(mbc_type: u8) -> &'static str {
+    match mbc_type {
+        0x00 => "ROM ONLY",
+        0x01 => "MBC1",
+        0x02 => "MBC1+RAM",
+        0x03 => "MBC1+RAM+BATTERY",
+        0x05 => "MBC2",
+        0x06 => "MBC2+BATTERY",
+        0x08 => "ROM+RAM",
+        0x09 => "ROM+RAM+BATTERY",
+        0x0B => "MMM01",
+        0x0C => "MMM01+RAM",
+        0x0D => "MMM01+RAM+BATTERY",
+        0x0F => "MBC3+TIMER+BATTERY",
+        0x10 => "MBC3+TIMER+RAM+BATTERY",
+        0x11 => "MBC3",
+        0x12 => "MBC3+RAM",
+        0x13 => "MBC3+RAM+BATTERY",
+        0x19 => "MBC5",
+        0x1A => "MBC5+RAM",
+        0x1B => "MBC5+RAM+BATTERY",
+        0x1C => "MBC5+RUMBLE",
+        0x1D => "MBC5+RUMBLE+RAM",
+        0x1E => "MBC5+RUMBLE+RAM+BATTERY",
+        0x20 => "MBC6",
+        0x22 => "MBC7+SENSOR+RUMBLE+RAM+BATTERY",
+        0xFC => "POCKET CAMERA",
+        0xFD => "BANDAI TAMA5",
+        0xFE => "HuC3",
+        0xFF => "HuC1+RAM+BATTERY",
+        _ => "unknown",
+    }
+}
+
+/// prints the header so a rom can be looked at without running it
+pub fn print_info(rom: &[u8]) {
+    let header = Header::new(rom);
+    println!("title:            {}", header.title);
+    if let Some(code) = &header.manufacturer_code {
+        println!("manufacturer:     {code}");
+    }
+    println!("cgb flag:         {:02X}", header.cgb_flag);
+    println!("sgb flag:         {:02X}", header.sgb_flag);
+    println!("licensee:         {}", header.licensee);
+    println!("cartridge type:   {:02X} ({})", header.mbc_type, mbc_name(header.mbc_type));
+    match header.rom_banks() {
+        Ok(banks) => println!("rom size:         {} ({banks} banks)", format_size(banks * 0x4000)),
+        Err(_) => println!("rom size:         unknown ({:02X})", header.rom_size_code),
+    }
+    match header.ram_size() {
+        Ok(size) => println!("ram size:         {}", format_size(size)),
+        Err(_) => println!("ram size:         unknown ({:02X})", header.ram_size_code),
+    }
+    println!("version:          {:02X}", header.version);
+    println!("header checksum:  {:02X}", header.header_checksum);
+    println!("global checksum:  {:04X}", header.global_checksum);
+
+    if let Ok(banks) = header.rom_banks() {
+        if rom.len() < banks * 0x4000 {
+            eprintln!("warning: {}", CartridgeError::Truncated { file: rom.len(), header: banks * 0x4000 });
+        }
+    }
+    if !header.has_valid_logo() {
+        eprintln!("warning: the logo is wrong, a real gameboy would not boot this");
+    }
+    let header_sum = header_checksum(rom);
+    if header_sum != header.header_checksum {
+        eprintln!("warning: header checksum should be {header_sum:02X}, a real gameboy would not boot this");
+    }
+    let global_sum = global_checksum(rom);
+    if global_sum != header.global_checksum {
+        eprintln!("warning: global checksum should be {global_sum:04X}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom_with_title(title: &[u8], cgb_flag: u8) -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x134..0x134 + title.len()].copy_from_slice(title);
+        rom[0x143] = cgb_flag;
+        rom
+    }
+
+    #[test]
+    fn old_title_uses_all_16_bytes() {
+        let header = Header::new(&rom_with_title(b"SIXTEEN LETTERS!", 0x21));
+        assert_eq!(header.title, "SIXTEEN LETTERS!");
+        assert_eq!(header.manufacturer_code, None);
+    }
+
+    #[test]
+    fn colour_title_stops_before_the_flag() {
+        let header = Header::new(&rom_with_title(b"POKEMON YELLOW", 0x80));
+        assert_eq!(header.title, "POKEMON YELLOW");
+        assert_eq!(header.manufacturer_code, None);
+    }
+
+    #[test]
+    fn manufacturer_code_after_the_title() {
+        let header = Header::new(&rom_with_title(b"ZELDA\0\0\0\0\0\0AZ7E", 0xC0));
+        assert_eq!(header.title, "ZELDA");
+        assert_eq!(header.manufacturer_code.as_deref(), Some("AZ7E"));
+    }
+
+    #[test]
+    fn lowercase_isnt_a_manufacturer_code() {
+        let header = Header::new(&rom_with_title(b"MARIO DELUXEabcd", 0x80));
+        assert_eq!(header.title, "MARIO DELUXEabc");
+        assert_eq!(header.manufacturer_code, None);
+    }
+
+    #[test]
+    fn new_licensee() {
+        let mut rom = rom_with_title(b"TITLE", 0x00);
+        rom[0x144..0x146].copy_from_slice(b"01");
+        rom[0x14B] = 0x33;
+        assert_eq!(Header::new(&rom).licensee, "01");
+        rom[0x14B] = 0x08;
+        assert_eq!(Header::new(&rom).licensee, "08");
+    }
+}
@@ -0,0 +1,137 @@
+use std::path::Path;
+
+/// the two kinds of codes people share.
+/// game genie codes sit between the cartridge and the gameboy, changing
+/// what gets read out of the rom. Gameshark codes keep writing to the ram
+/// every frame, the type byte can also pick which ram bank the code is for:
+/// 0x01 is any bank, 0x8X is cartridge ram bank X and 0x9X is wram bank X
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheatKind {
+    GameGenie { address: u16, data: u8, compare: Option<u8> },
+    GameShark { bank: Option<u8>, address: u16, data: u8 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Cheat {
+    pub code: String,
+    pub name: String,
+    pub kind: CheatKind,
+    pub enabled: bool,
+}
+
+/// works out which kind of code it is from how it looks
+pub fn parse_code(code: &str) -> Result<CheatKind, String> {
+    let code = code.trim();
+    if code.contains('-') {
+        return parse_game_genie(code);
+    }
+    parse_gameshark(code)
+}
+
+/// `ABC-DEF-GHI` or `ABC-DEF` without the compare byte.
+/// AB is the new data, FCDE is the address with F inverted, and
+/// G and I make up the compare byte which is scrambled a bit
+fn parse_game_genie(code: &str) -> Result<CheatKind, String> {
+    let digits: String = code.chars().filter(|c| *c != '-').collect();
+    if !(digits.len() == 6 || digits.len() == 9) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("{code} is not a game genie code"));
+    }
+    let digit = |i: usize| u16::from_str_radix(&digits[i..i + 1], 16).unwrap();
+
+    let data = (digit(0) << 4 | digit(1)) as u8;
+    let address = (digit(5) ^ 0xF) << 12 | digit(2) << 8 | digit(3) << 4 | digit(4);
+    if address > 0x7FFF {
+        return Err(format!("{code} does not point at the rom"));
+    }
+    let compare = (digits.len() == 9).then(|| {
+        let scrambled = (digit(6) << 4 | digit(8)) as u8;
+        scrambled.rotate_right(2) ^ 0xBA
+    });
+    Ok(CheatKind::GameGenie { address, data, compare })
+}
+
+/// `TTVVLLHH`, the type, the value and then the address little endian
+fn parse_gameshark(code: &str) -> Result<CheatKind, String> {
+    if code.len() != 8 || !code.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("{code} is not a gameshark code"));
+    }
+    let byte = |i: usize| u8::from_str_radix(&code[i..i + 2], 16).unwrap();
+    let address = (byte(6) as u16) << 8 | byte(4) as u16;
+    if address < 0x8000 {
+        return Err(format!("{code} does not point at the ram"));
+    }
+    let bank = match byte(0) {
+        0x01 => None,
+        kind @ (0x80..=0x9F) => Some(kind & 0x0F),
+        kind => return Err(format!("{code} has an unknown type {kind:02X}")),
+    };
+    Ok(CheatKind::GameShark { bank, address, data: byte(2) })
+}
+
+/// the cheats for a rom are kept next to it in a .cht file, one code a line
+/// with an optional name after it. Lines starting with # are ignored and
+/// codes starting with - start off disabled
+pub fn load_cheats(rom_path: &Path) -> Vec<Cheat> {
+    let path = rom_path.with_extension("cht");
+    let Ok(file) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+
+    let mut cheats = Vec::new();
+    for line in file.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (enabled, line) = match line.strip_prefix('-') {
+            Some(line) => (false, line),
+            None => (true, line),
+        };
+        let (code, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match parse_code(code) {
+            Ok(kind) => cheats.push(Cheat {
+                code: code.to_string(),
+                name: name.trim().to_string(),
+                kind,
+                enabled,
+            }),
+            Err(e) => eprintln!("skipping a cheat in {}: {e}", path.display()),
+        }
+    }
+    cheats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_genie_with_compare() {
+        // the compare byte is C9 rotated right by 2 and xored with BA
+        let kind = parse_code("00A-17B-C49").unwrap();
+        assert_eq!(kind, CheatKind::GameGenie { address: 0x4A17, data: 0x00, compare: Some(0xC8) });
+    }
+
+    #[test]
+    fn game_genie_without_compare() {
+        let kind = parse_code("3EA-17B").unwrap();
+        assert_eq!(kind, CheatKind::GameGenie { address: 0x4A17, data: 0x3E, compare: None });
+    }
+
+    #[test]
+    fn gameshark_any_bank_and_wram_bank() {
+        assert_eq!(parse_code("010A2CD0").unwrap(), CheatKind::GameShark { bank: None, address: 0xD02C, data: 0x0A });
+        assert_eq!(parse_code("910A2CD0").unwrap(), CheatKind::GameShark { bank: Some(1), address: 0xD02C, data: 0x0A });
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        // too few digits, not hex, and a game genie address outside the rom
+        assert!(parse_code("00A-17B-C4").is_err());
+        assert!(parse_code("00G-17B").is_err());
+        assert!(parse_code("00A-173").is_err());
+        // gameshark codes have to be 8 digits, for the ram, with a known type
+        assert!(parse_code("010A2CD").is_err());
+        assert!(parse_code("010A0040").is_err());
+        assert!(parse_code("420A2CD0").is_err());
+    }
+}
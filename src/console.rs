@@ -0,0 +1,169 @@
+use std::io::BufRead;
+use std::sync::mpsc::{channel, Receiver};
+use crate::cheats::{parse_code, Cheat};
+use crate::memory::Memory;
+use crate::search::{Filter, RamSearch, Width};
+use crate::watch::{Watch, WatchList};
+
+/// how many search results get printed at once
+const RESULTS_SHOWN: usize = 20;
+
+const HELP: &str = "\
+search 8|16          start a new search over the ram
+equal                keep what is the same as last time
+changed              keep what has changed since last time
+increased            keep what has gone up since last time
+decreased            keep what has gone down since last time
+value <n>            keep what is exactly n
+results              show what is left
+cheat [bank:]<address> <n>
+                     keep writing n to an address, 16 bits wide after a search 16
+watch [bank:]<address> <type> <name>
+                     show a value on screen, the type is u8, u16le, u16be, bcd or signed
+unwatch <n>          stop showing the nth watch
+watches              list the watches";
+
+/// commands typed into the terminal while the game is running. They get read
+/// on another thread so the emulator doesnt wait for them
+pub struct Console {
+    receiver: Receiver<String>,
+    search: Option<RamSearch>,
+}
+impl Console {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { receiver, search: None }
+    }
+
+    /// runs everything that has been typed since last frame
+    pub fn update(&mut self, memory: &mut Memory, watches: &mut WatchList) {
+        while let Ok(line) = self.receiver.try_recv() {
+            if let Err(e) = self.run_command(&line, memory, watches) {
+                println!("{e}");
+            }
+        }
+    }
+
+    fn run_command(&mut self, line: &str, memory: &mut Memory, watches: &mut WatchList) -> Result<(), String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let filter = match words.as_slice() {
+            [] => return Ok(()),
+            ["search", "8"] => {
+                self.search = Some(RamSearch::new(memory, Width::Byte));
+                return self.print_results();
+            }
+            ["search", "16"] => {
+                self.search = Some(RamSearch::new(memory, Width::Word));
+                return self.print_results();
+            }
+            ["equal"] => Filter::Equal,
+            ["changed"] => Filter::Changed,
+            ["increased"] => Filter::Increased,
+            ["decreased"] => Filter::Decreased,
+            ["value", value] => Filter::Value(parse_value(value)?),
+            ["results"] => return self.print_results(),
+            ["cheat", location, value] => {
+                let (bank, address) = parse_location(location)?;
+                let value = parse_value(value)?;
+                // a value found with a 16 bit search needs both bytes kept, even when it is small
+                let width = match &self.search {
+                    Some(search) => search.width(),
+                    None if value > 0xFF => Width::Word,
+                    None => Width::Byte,
+                };
+                return add_ram_cheat(memory, bank, address, value, width);
+            }
+            ["watch", ..] => {
+                watches.add(Watch::parse(line.trim_start().trim_start_matches("watch"))?);
+                return Ok(());
+            }
+            ["unwatch", index] => {
+                let index: usize = index.parse().map_err(|_| format!("{index} is not a number"))?;
+                let watch = index.checked_sub(1)
+                    .and_then(|index| watches.remove(index))
+                    .ok_or(format!("there is no watch {index}"))?;
+                println!("stopped watching {}", watch.name);
+                return Ok(());
+            }
+            ["watches"] => {
+                for (i, watch) in watches.watches().iter().enumerate() {
+                    println!("  {} {}", i + 1, watch.to_line());
+                }
+                return Ok(());
+            }
+            _ => return Err(HELP.to_string()),
+        };
+
+        let search = self.search.as_mut().ok_or("start a search first with search 8 or search 16")?;
+        search.filter(memory, filter);
+        self.print_results()
+    }
+
+    fn print_results(&self) -> Result<(), String> {
+        let search = self.search.as_ref().ok_or("start a search first with search 8 or search 16")?;
+        let candidates = search.candidates();
+        println!("{} addresses left", candidates.len());
+        for (location, value) in candidates.iter().take(RESULTS_SHOWN) {
+            match search.width() {
+                Width::Byte => println!("  {location} = {value} ({value:02X})"),
+                Width::Word => println!("  {location} = {value} ({value:04X})"),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// turns the value into gameshark codes, two of them for a 16 bit value
+fn add_ram_cheat(memory: &mut Memory, bank: Option<u8>, address: u16, value: u16, width: Width) -> Result<(), String> {
+    let bytes: &[u8] = match width {
+        Width::Word => &value.to_le_bytes(),
+        Width::Byte if value > 0xFF => return Err(format!("{value} does not fit in a byte")),
+        Width::Byte => &[value as u8],
+    };
+    let kind = match bank {
+        None => 0x01,
+        Some(bank @ 0x0..=0xF) => 0x80 | bank,
+        Some(bank) => return Err(format!("gameshark codes cant reach bank {bank:X}")),
+    };
+    for (i, byte) in bytes.iter().enumerate() {
+        let [low, high] = address.wrapping_add(i as u16).to_le_bytes();
+        let code = format!("{kind:02X}{byte:02X}{low:02X}{high:02X}");
+        let kind = parse_code(&code)?;
+        memory.add_cheat(Cheat { code: code.clone(), name: String::new(), kind, enabled: true });
+        println!("cheat {} {code} is on", memory.cheats().len());
+    }
+    Ok(())
+}
+
+/// addresses are always hex, like in every debugger
+fn parse_address(text: &str) -> Result<u16, String> {
+    let digits = text.trim_start_matches("0x");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("{text} is not an address"))
+}
+
+/// an address with an optional bank in front, like the search prints them
+fn parse_location(text: &str) -> Result<(Option<u8>, u16), String> {
+    match text.split_once(':') {
+        Some((bank, address)) => {
+            let bank = u8::from_str_radix(bank, 16).map_err(|_| format!("{bank} is not a bank"))?;
+            Ok((Some(bank), parse_address(address)?))
+        }
+        None => Ok((None, parse_address(text)?)),
+    }
+}
+
+/// values are decimal unless they start with 0x
+fn parse_value(text: &str) -> Result<u16, String> {
+    let value = match text.strip_prefix("0x") {
+        Some(digits) => u16::from_str_radix(digits, 16),
+        None => text.parse(),
+    };
+    value.map_err(|_| format!("{text} is not a number"))
+}
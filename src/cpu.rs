@@ -9,14 +9,7 @@ pub struct Cpu {
     pub halt: bool,
 }
 impl Cpu {
-    pub fn new(booted: bool) -> Self {
-        let regs;
-
-        if booted {
-            regs = Registers::booted();
-        } else {
-            regs = Registers::default();
-        }
+    pub fn new(regs: Registers) -> Self {
         Self {
             regs,
             ..Default::default()
@@ -55,20 +48,6 @@ impl Default for Registers {
     }  
 }
 impl Registers {
-    pub fn booted() -> Self {
-        Self {
-            a: 0x01, 
-            b: 0xFF, 
-            c: 0x13, 
-            d: 0x00, 
-            e: 0xC1, 
-            f: Flag::from_u8(0), 
-            h: 0x84, 
-            l: 0x03, 
-            pc: 0x100, 
-            sp: 0xFFFE,
-        }
-    }
     pub fn set_bc(&mut self, bc: u16) { (self.b, self.c) = split(bc) }
     pub fn get_bc(&self) -> u16 { combine(self.b, self.c) }
 
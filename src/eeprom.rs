@@ -0,0 +1,251 @@
+/// the 93LC56 eeprom in mbc7 cartridges, 128 words of 16 bits. The game talks
+/// to it one bit at a time, everything happens on the rising edge of the clock.
+/// the commands are from https://gbdev.io/pandocs/MBC7.html
+pub struct Eeprom {
+    data: Vec<u16>,
+    write_enabled: bool,
+
+    // the pins, as last written by the game
+    cs: bool,
+    clk: bool,
+    di: bool,
+    do_bit: bool,
+
+    state: EepromState,
+}
+
+enum EepromState {
+    // waiting for the start bit
+    Idle,
+    // the 2 bit opcode and the 8 bit address
+    Command { bits: u16, count: u8 },
+    Read { word: u16, count: u8 },
+    // no address means every word gets written
+    Write { address: Option<usize>, bits: u16, count: u8 },
+}
+
+impl Eeprom {
+    pub fn new() -> Self {
+        Self {
+            // a blank eeprom has every bit set
+            data: vec![0xFFFF; 128],
+            write_enabled: false,
+            cs: false,
+            clk: false,
+            di: false,
+            do_bit: true,
+            state: EepromState::Idle,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        (self.cs as u8) << 7 | (self.clk as u8) << 6 | (self.di as u8) << 1 | self.do_bit as u8
+    }
+
+    pub fn write(&mut self, data: u8) {
+        let cs = data & 0x80 != 0;
+        let clk = data & 0x40 != 0;
+        self.di = data & 0x02 != 0;
+
+        // dropping chip select cancels whatever was going on
+        if !cs {
+            self.state = EepromState::Idle;
+            self.do_bit = true;
+        } else if clk && !self.clk {
+            self.clock();
+        }
+        self.cs = cs;
+        self.clk = clk;
+    }
+
+    fn clock(&mut self) {
+        let di = self.di as u16;
+        self.state = match std::mem::replace(&mut self.state, EepromState::Idle) {
+            EepromState::Idle if di == 1 => EepromState::Command { bits: 0, count: 0 },
+            EepromState::Idle => EepromState::Idle,
+            EepromState::Command { bits, count } => {
+                let bits = bits << 1 | di;
+                match count + 1 {
+                    10 => self.command(bits),
+                    count => EepromState::Command { bits, count },
+                }
+            }
+            EepromState::Read { word, count } => {
+                self.do_bit = word & 0x8000 != 0;
+                match count + 1 {
+                    16 => EepromState::Idle,
+                    count => EepromState::Read { word: word << 1, count },
+                }
+            }
+            EepromState::Write { address, bits, count } => {
+                let bits = bits << 1 | di;
+                if count + 1 < 16 {
+                    EepromState::Write { address, bits, count: count + 1 }
+                } else {
+                    if self.write_enabled {
+                        match address {
+                            Some(address) => self.data[address] = bits,
+                            None => self.data.fill(bits),
+                        }
+                    }
+                    // writes finish instantly, so it is always ready
+                    self.do_bit = true;
+                    EepromState::Idle
+                }
+            }
+        };
+    }
+
+    fn command(&mut self, bits: u16) -> EepromState {
+        // only 7 of the address bits are used on a 128 word eeprom
+        let address = (bits & 0x7F) as usize;
+        match (bits >> 8) & 0b11 {
+            // READ, a dummy 0 comes out before the data
+            0b10 => {
+                self.do_bit = false;
+                EepromState::Read { word: self.data[address], count: 0 }
+            }
+            // WRITE
+            0b01 => EepromState::Write { address: Some(address), bits: 0, count: 0 },
+            // ERASE
+            0b11 => {
+                if self.write_enabled {
+                    self.data[address] = 0xFFFF;
+                }
+                self.do_bit = true;
+                EepromState::Idle
+            }
+            // the rest use the top of the address to pick what they do
+            _ => match (bits >> 6) & 0b11 {
+                0b11 => { self.write_enabled = true; EepromState::Idle }
+                0b00 => { self.write_enabled = false; EepromState::Idle }
+                // ERAL
+                0b10 => {
+                    if self.write_enabled {
+                        self.data.fill(0xFFFF);
+                    }
+                    self.do_bit = true;
+                    EepromState::Idle
+                }
+                // WRAL
+                _ => EepromState::Write { address: None, bits: 0, count: 0 },
+            },
+        }
+    }
+
+    /// the words are saved little endian one after another
+    pub fn save(&self) -> Vec<u8> {
+        self.data.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    pub fn load(&mut self, data: &[u8]) {
+        for (word, bytes) in self.data.iter_mut().zip(data.chunks_exact(2)) {
+            *word = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CS: u8 = 0x80;
+    const CLK: u8 = 0x40;
+    const DI: u8 = 0x02;
+
+    /// clocks bits in, most significant first
+    fn send(eeprom: &mut Eeprom, value: u32, bits: u8) {
+        for i in (0..bits).rev() {
+            let di = if value >> i & 1 != 0 { DI } else { 0 };
+            eeprom.write(CS | di);
+            eeprom.write(CS | CLK | di);
+        }
+    }
+
+    /// the start bit, then the 2 bit opcode and the 8 bit address
+    fn command(eeprom: &mut Eeprom, opcode: u32, address: u32) {
+        send(eeprom, 1 << 10 | opcode << 8 | address, 11);
+    }
+
+    fn deselect(eeprom: &mut Eeprom) {
+        eeprom.write(0);
+    }
+
+    fn read_word(eeprom: &mut Eeprom, address: u32) -> u16 {
+        command(eeprom, 0b10, address);
+        // the dummy 0 comes out as soon as the address is in
+        assert_eq!(eeprom.read() & 1, 0);
+        let mut word = 0;
+        for _ in 0..16 {
+            eeprom.write(CS);
+            eeprom.write(CS | CLK);
+            word = word << 1 | (eeprom.read() & 1) as u16;
+        }
+        deselect(eeprom);
+        word
+    }
+
+    fn write_word(eeprom: &mut Eeprom, address: u32, word: u16) {
+        command(eeprom, 0b01, address);
+        send(eeprom, word as u32, 16);
+        deselect(eeprom);
+    }
+
+    #[test]
+    fn writes_need_enabling() {
+        let mut eeprom = Eeprom::new();
+        write_word(&mut eeprom, 0x12, 0x1234);
+        assert_eq!(read_word(&mut eeprom, 0x12), 0xFFFF);
+
+        // EWEN
+        command(&mut eeprom, 0b00, 0xC0);
+        deselect(&mut eeprom);
+        write_word(&mut eeprom, 0x12, 0x1234);
+        assert_eq!(read_word(&mut eeprom, 0x12), 0x1234);
+
+        // EWDS
+        command(&mut eeprom, 0b00, 0x00);
+        deselect(&mut eeprom);
+        write_word(&mut eeprom, 0x12, 0xABCD);
+        assert_eq!(read_word(&mut eeprom, 0x12), 0x1234);
+    }
+
+    #[test]
+    fn erase_and_write_all() {
+        let mut eeprom = Eeprom::new();
+        command(&mut eeprom, 0b00, 0xC0);
+        deselect(&mut eeprom);
+
+        // WRAL
+        command(&mut eeprom, 0b00, 0x40);
+        send(&mut eeprom, 0x5A5A, 16);
+        deselect(&mut eeprom);
+        assert_eq!(read_word(&mut eeprom, 0x00), 0x5A5A);
+        assert_eq!(read_word(&mut eeprom, 0x7F), 0x5A5A);
+
+        // ERASE
+        command(&mut eeprom, 0b11, 0x7F);
+        deselect(&mut eeprom);
+        assert_eq!(read_word(&mut eeprom, 0x7F), 0xFFFF);
+        assert_eq!(read_word(&mut eeprom, 0x00), 0x5A5A);
+
+        // ERAL
+        command(&mut eeprom, 0b00, 0x80);
+        deselect(&mut eeprom);
+        assert_eq!(read_word(&mut eeprom, 0x00), 0xFFFF);
+    }
+
+    #[test]
+    fn save_round_trip() {
+        let mut eeprom = Eeprom::new();
+        command(&mut eeprom, 0b00, 0xC0);
+        deselect(&mut eeprom);
+        write_word(&mut eeprom, 0x01, 0xBEEF);
+        let save = eeprom.save();
+        assert_eq!(&save[2..4], &[0xEF, 0xBE]);
+
+        let mut loaded = Eeprom::new();
+        loaded.load(&save);
+        assert_eq!(read_word(&mut loaded, 0x01), 0xBEEF);
+    }
+}
@@ -1,4 +1,5 @@
 use std::usize;
+use std::collections::VecDeque;
 use crate::memory::Memory;
 use crate::util::ppu::*;
 use crate::util::INTERRUPT_F_ADDRESS;
@@ -33,18 +34,79 @@ fn to_palette(index: u8, palette: u8) -> u8 {
     lcdc_color
 }
 
-#[derive(Debug)]
-enum PpuState {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PpuState {
     Oam,
     HBlank,
     VBlank,
     Drawing,
 }
 
+/// a write to one of the ppu's registers, cycle is how many dots into the
+/// cpu step it happened. Memory keeps these so the ppu can catch up to them
+#[derive(Debug, Clone, Copy)]
+pub struct PpuWrite {
+    pub cycle: u8,
+    pub address: u16,
+    pub old: u8,
+    pub data: u8,
+}
+
+/// a sprite pixel waiting in the object fifo
+#[derive(Debug, Clone, Copy, Default)]
+struct ObjPixel {
+    color: u8,
+    palette_1: bool,
+    behind_bg: bool,
+}
+
+/// the steps the fetcher goes through for 8 pixels of background or window.
+/// each one takes 2 dots apart from pushing, which waits for the fifo to empty
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FetchStep {
+    TileNumber,
+    DataLow,
+    DataHigh,
+    Push,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Fetcher {
+    step: FetchStep,
+    // the dots spent on the current step
+    dots: u8,
+    // how many tiles into the line (or window) the fetcher is
+    tile_x: u8,
+    tile_number: u8,
+    low: u8,
+    high: u8,
+    window: bool,
+}
+impl Fetcher {
+    fn new(window: bool) -> Self {
+        Self { step: FetchStep::TileNumber, dots: 0, tile_x: 0, tile_number: 0, low: 0, high: 0, window }
+    }
+}
+
 pub struct Ppu {
     ticks: usize,
     state: PpuState,
-    window_line: usize
+    window_line: usize,
+    // whether the window showed up on this line, the window line only goes up when it does
+    window_drawn: bool,
+    fetcher: Fetcher,
+    bg_fifo: VecDeque<u8>,
+    obj_fifo: VecDeque<ObjPixel>,
+    // the pixels at the start of the line which get thrown away for scx
+    discard: u8,
+    // dots left where the fifos are stopped, while a sprite gets fetched
+    stall: u8,
+    // the background tile the last sprite fetch waited on, a tile only holds things up once
+    stall_tile: Option<usize>,
+    // the sprites found on this line during the oam scan
+    sprites: Vec<[u8; 4]>,
+    // the pixels pushed to the lcd so far
+    line: Vec<u8>,
 }
 impl Ppu {
     pub fn default() -> Self {
@@ -52,16 +114,91 @@ impl Ppu {
             ticks: 0,
             state: PpuState::Oam,
             window_line: 0,
+            window_drawn: false,
+            fetcher: Fetcher::new(false),
+            bg_fifo: VecDeque::new(),
+            obj_fifo: VecDeque::new(),
+            discard: 0,
+            stall: 0,
+            stall_tile: None,
+            sprites: Vec::new(),
+            line: Vec::new(),
         }
     }
+    /// puts the ppu at a line and dot in the frame, like the boot rom would have
+    pub fn at_position(mem: &mut Memory, ly: u8, dot: usize) -> Self {
+        let mut ppu = Ppu::default();
+        if ly >= 144 {
+            ppu.ticks = (ly as usize - 144) * HBLANK_CYCLES + dot;
+            set_state(&mut ppu, mem, PpuState::VBlank);
+            stat_interrupt(mem, 0, 1);
+            return ppu;
+        }
+        ppu.ticks = dot;
+        match dot {
+            0..OAM_CYCLES => {
+                set_state(&mut ppu, mem, PpuState::Oam);
+                stat_interrupt(mem, 0, 2);
+            }
+            _ => {
+                // how long drawing takes depends on the line, so the fifo has to get there
+                set_state(&mut ppu, mem, PpuState::Drawing);
+                stat_interrupt(mem, 0, 3);
+                for _ in OAM_CYCLES..dot {
+                    if ppu.line.len() >= 160 {
+                        break;
+                    }
+                    draw_dot(&mut ppu, mem);
+                }
+                if ppu.line.len() >= 160 {
+                    set_state(&mut ppu, mem, PpuState::HBlank);
+                    stat_interrupt(mem, 0, 0);
+                }
+            }
+        }
+        ppu
+    }
     pub fn line_reset(&mut self) {
         self.state = PpuState::Oam;
         self.ticks = 0;
     }
 }
 
-/// updates the ppu, called in between instructions
+/// changes the mode of the ppu, memory needs to know about it
+/// as it decides what the cpu is allowed to access
+fn set_state(ppu: &mut Ppu, mem: &mut Memory, state: PpuState) {
+    ppu.state = state;
+    mem.set_ppu_state(state);
+    if state == PpuState::Oam {
+        mem.set_oam_scan_row(0);
+    }
+    if state == PpuState::Drawing {
+        start_drawing(ppu, mem);
+    }
+}
+
+/// updates the ppu, called in between instructions. The registers the cpu wrote
+/// to during the instruction get put back how they were and then written again
+/// at the dot it happened on, so changes in the middle of a line land on the right pixel
 pub fn update_ppu(ppu: &mut Ppu, mem: &mut Memory, ticks: u8) -> Option<Vec<u8>> {
+    let mut writes = mem.take_ppu_writes().into_iter().peekable();
+    let mut line = None;
+    for dot in 0..ticks {
+        while let Some(write) = writes.next_if(|write| write.cycle <= dot) {
+            mem.replay_ppu_write(&write);
+        }
+        if let Some(pixels) = update_dot(ppu, mem) {
+            line = Some(pixels);
+        }
+    }
+    for write in writes {
+        mem.replay_ppu_write(&write);
+    }
+    line
+}
+
+/// moves the ppu along by a single dot
+fn update_dot(ppu: &mut Ppu, mem: &mut Memory) -> Option<Vec<u8>> {
     use PpuState::*;
 
     let lcdc = mem.unchecked_read(PpuRegisters::LCDC as u16);
@@ -84,22 +221,30 @@ pub fn update_ppu(ppu: &mut Ppu, mem: &mut Memory, ticks: u8) -> Option<Vec<u8>>
         mem.write(PpuRegisters::STAT as u16, stat);
     }
 
-    ppu.ticks += ticks as usize;
+    ppu.ticks += 1;
     match ppu.state {
         Oam => {
             if ppu.ticks < OAM_CYCLES {
+                // each row of oam takes the ppu 4 dots to scan
+                mem.set_oam_scan_row(ppu.ticks / 4);
                 return None;
             }
-            ppu.state = PpuState::Drawing;
+            set_state(ppu, mem, PpuState::Drawing);
             stat_interrupt(mem, 0, 3);
         },
         Drawing => {
-            if ppu.ticks < DRAW_CYCLES {
+            // drawing goes on until the whole line is out, which is longer with
+            // scx, the window and sprites. Hblank is whatever is left of the line
+            draw_dot(ppu, mem);
+            if ppu.line.len() < 160 {
                 return None;
             }
             stat_interrupt(mem, STAT_HBLANK, 0);
-            ppu.state = PpuState::HBlank;
-            return Some(draw(ppu, mem));
+            set_state(ppu, mem, PpuState::HBlank);
+            if ppu.window_drawn {
+                ppu.window_line += 1;
+            }
+            return Some(std::mem::take(&mut ppu.line));
         },
         HBlank => {
             if ppu.ticks < HBLANK_CYCLES {
@@ -111,9 +256,10 @@ pub fn update_ppu(ppu: &mut Ppu, mem: &mut Memory, ticks: u8) -> Option<Vec<u8>>
             
             if ly == 143 {
                 stat_interrupt(mem, STAT_VBLANK, 1);
-                ppu.state = PpuState::VBlank;
+                set_state(ppu, mem, PpuState::VBlank);
                 return None;
             } 
+            set_state(ppu, mem, PpuState::Oam);
             stat_interrupt(mem, STAT_OAM, 2);
         }, // waits
         VBlank => {    
@@ -124,86 +270,21 @@ pub fn update_ppu(ppu: &mut Ppu, mem: &mut Memory, ticks: u8) -> Option<Vec<u8>>
             }
             stat_interrupt(mem, STAT_OAM, 2);
             *ppu = Ppu::default();
+            set_state(ppu, mem, PpuState::Oam);
             mem.write(PpuRegisters::LY as u16, 0)
         }, // waits
     }
     return None;
 }
 
-fn draw(ppu: &mut Ppu, mem: &mut Memory) -> Vec<u8> {
-    // this bit in the middle will do all the drawing
+/// gets the fifos ready for a new line and picks out the
+/// (up to 10) sprites which are on it
+fn start_drawing(ppu: &mut Ppu, mem: &Memory) {
     let lcdc = mem.unchecked_read(PpuRegisters::LCDC as u16);
-    let ly = mem.unchecked_read(PpuRegisters::LY as u16);
-
-    // the number of pixels pushed to the lcd
-    let mut screen_pixels: Vec<u8> = Vec::new();
-
-    let background_pixels = draw_background(mem, lcdc, ly);
-    let window_pixels = draw_window(ppu, mem, lcdc, ly);
-
-    // also the palle
-    let bg_palette = mem.unchecked_read(PpuRegisters::BGP as u16);
-
-    let sprite_pixels = draw_sprites(mem, lcdc, ly);
-    let sprite_palette_0 = mem.unchecked_read(PpuRegisters::OBP0 as u16);
-    let sprite_palette_1 = mem.unchecked_read(PpuRegisters::OBP1 as u16);
-
-
-    // each pixel is decided sequentially
-    for i in 0..160 {
-        let win_pixel = window_pixels[i];
-        let sprite_pixel = sprite_pixels[i];
-        let bg_pixel = background_pixels[i];
-
-        let sprite_pallete_i = sprite_pixel & 0b0001_0000 == 0;
-        let sprite_pallete = match sprite_pallete_i {
-            true => sprite_palette_0,
-            false => sprite_palette_1,
-        };
-
-        // sprite pixel is the priority
-        if sprite_pixel & 0b1000_0000 == 0 {
-            let sprite_color = sprite_pixel & 0b0000_0011;
-
-            // empty
-            if sprite_color == 0 || sprite_color == BLANK_PIXEL {
-                if win_pixel != BLANK_PIXEL {
-                    screen_pixels.push(to_palette(win_pixel, bg_palette));
-                    continue;
-                }
-                screen_pixels.push(to_palette(bg_pixel, bg_palette));
-                continue;
-            }
-            screen_pixels.push(to_palette(sprite_color, sprite_pallete));
-            continue;
-        }
-        if sprite_pixel == BLANK_PIXEL {
-            screen_pixels.push(match win_pixel {
-                BLANK_PIXEL => to_palette(bg_pixel, bg_palette),
-                _ => to_palette(win_pixel, bg_palette),
-            });
-            continue;
-        }
-        // there is a sprite pixel, but 1-3 of bg & window draw over it
-        screen_pixels.push(match (win_pixel, bg_pixel) {
-            (0|BLANK_PIXEL, 0|BLANK_PIXEL) => to_palette(sprite_pixel&3, sprite_pallete),
-            (_, 0|BLANK_PIXEL) => to_palette(win_pixel, bg_palette),
-            (0|BLANK_PIXEL, _) => to_palette(bg_pixel, bg_palette),
-            _ => to_palette(win_pixel, bg_palette),
-        });
-    }
-    return screen_pixels;
-}
-
-fn draw_sprites(mem: &Memory, lcdc: u8, ly: u8) -> Vec<u8> {
-    let ly = ly + 16;
-    if lcdc & 0b0000_0010 == 0 {
-        return vec![BLANK_PIXEL; 160];
-    }
+    let ly = mem.unchecked_read(PpuRegisters::LY as u16) + 16;
     let obj_size = if lcdc & 0b0000_0100 == 0 { 8 } else { 16 };
 
-    // oam scan
-    let mut oam_buffer = Vec::new();
+    ppu.sprites.clear();
     for i in 0..40 {
         let oam_sprite = mem.oam_search(i);
 
@@ -213,137 +294,217 @@ fn draw_sprites(mem: &Memory, lcdc: u8, ly: u8) -> Vec<u8> {
         if ly < oam_sprite[0] { continue; } // ly+16 must be greater than or equal to sprite y-position
         if ly >= oam_sprite[0] + obj_size { continue; } // ly+16 must be less than sprite y-position + sprite height
 
-        oam_buffer.push(oam_sprite);
-        if oam_buffer.len() == 10 {
+        ppu.sprites.push(oam_sprite);
+        if ppu.sprites.len() == 10 {
             break; // only the first ten items are wanted
         }
     }
-    oam_buffer.sort_by(|a, b| a[1].cmp(&b[1]));
-
-    let mut sprite_pixels = vec![BLANK_PIXEL; 300]; // 160 (length of lcd + 8x2 pad)
-    for sprite in oam_buffer {
-        let tile_index;
-        if obj_size == 16 {
-            let base_tile_index = sprite[2] & 0b1111_1110;
-            let used_tile = ly >= sprite[0] + 8;
-            let reversed = sprite[3] & 0b0100_0000 != 0;
-            tile_index = base_tile_index | if reversed {!used_tile as u8} else {used_tile as u8};
-        } else {
-            tile_index = sprite[2];
-        }
-
-        let mut tile_data = mem.read_tile(0x8000 + (tile_index as u16*16));
-        if sprite[3] & 0b0100_0000 != 0 {
-            tile_data.reverse();
-        }
-        let row = (ly - sprite[0]) as usize % 8;
+    // the one further left wins, and then the one earlier in oam
+    ppu.sprites.sort_by(|a, b| a[1].cmp(&b[1]));
+
+    ppu.fetcher = Fetcher::new(false);
+    ppu.bg_fifo.clear();
+    ppu.obj_fifo.clear();
+    ppu.discard = mem.unchecked_read(PpuRegisters::SCX as u16) % 8;
+    // the first tile gets fetched twice, the first time is just thrown away
+    ppu.stall = 6;
+    ppu.stall_tile = None;
+    ppu.window_drawn = false;
+    ppu.line.clear();
+}
 
-        let row_data = tile_data[row];
-        let mut row_pixels = get_individual_pixels(row_data);
-        if sprite[3] & 0b0010_0000 != 0 {
-            row_pixels.reverse();
-        }
+/// one dot of mode 3, the fetcher moves along and a pixel gets pushed
+/// to the lcd if there is one ready
+fn draw_dot(ppu: &mut Ppu, mem: &Memory) {
+    if ppu.stall > 0 {
+        ppu.stall -= 1;
+        return;
+    }
+    let lcdc = mem.unchecked_read(PpuRegisters::LCDC as u16);
+    if !ppu.fetcher.window && window_starts(ppu, mem, lcdc) {
+        // the fetcher starts over with the window's tiles
+        ppu.fetcher = Fetcher::new(true);
+        ppu.bg_fifo.clear();
+        ppu.window_drawn = true;
+        ppu.stall_tile = None;
+        let wx = mem.unchecked_read(PpuRegisters::WX as u16);
+        ppu.discard = 7u8.saturating_sub(wx);
+    }
+    fetch(ppu, mem, lcdc);
 
-        for i in 0..8 {
-            let i = i as usize;
-            match sprite_pixels[i+sprite[1] as usize]&7 {
-                0 | BLANK_PIXEL => {
-                    let pixel_data = row_pixels[i] | sprite[3] & 0b1001_0000;
-                    sprite_pixels[i+sprite[1] as usize] = pixel_data;
-                },
-                _ => continue,
-            }
-        }
+    if ppu.bg_fifo.is_empty() {
+        return;
+    }
+    if ppu.discard > 0 {
+        ppu.bg_fifo.pop_front();
+        ppu.discard -= 1;
+        return;
+    }
+    // the pixel waits until the sprites on it have been fetched
+    ppu.stall = load_sprites(ppu, mem, lcdc);
+    if ppu.stall > 0 {
+        ppu.stall -= 1;
+        return;
     }
-    sprite_pixels[8..168].to_vec()
+
+    let bg_pixel = ppu.bg_fifo.pop_front().unwrap();
+    let obj_pixel = ppu.obj_fifo.pop_front();
+    ppu.line.push(mix_pixel(mem, lcdc, bg_pixel, obj_pixel));
 }
 
-fn draw_window(ppu: &mut Ppu, mem: &Memory, lcdc: u8, ly: u8) -> Vec<u8> {
-    // the window just isnt drawing
+/// the window takes over once the lcd gets to wx (which is 7 ahead)
+fn window_starts(ppu: &Ppu, mem: &Memory, lcdc: u8) -> bool {
     if lcdc & 0b0010_0001 != 0b0010_0001 {
-        return vec![BLANK_PIXEL; 160];
+        return false;
     }
-
     let wy = mem.unchecked_read(PpuRegisters::WY as u16);
-    let wx = mem.unchecked_read(PpuRegisters::WX as u16);
+    let wx = mem.unchecked_read(PpuRegisters::WX as u16) as usize;
+    let ly = mem.unchecked_read(PpuRegisters::LY as u16);
+    wy <= ly && wx <= 166 && ppu.line.len() + 7 >= wx
+}
 
-    // the window will display just not on this line yet
-    // or too far to the right to be displayed
-    if wy > ly || wx > 167 {
-        return vec![BLANK_PIXEL; 160];
+fn fetch(ppu: &mut Ppu, mem: &Memory, lcdc: u8) {
+    let fetcher = &mut ppu.fetcher;
+    if fetcher.step != FetchStep::Push {
+        fetcher.dots += 1;
+        if fetcher.dots < 2 {
+            return;
+        }
+        fetcher.dots = 0;
     }
 
-    let map_address = if lcdc & 0b0100_0000 != 0 { 0x9C00 } else { 0x9800 };
-    let addressing = if lcdc & 0b0001_0000 != 0 { 0x8000 } else { 0x8800 };
+    let ly = mem.unchecked_read(PpuRegisters::LY as u16);
+    // the row of pixels in the background or window being drawn
+    let y = match fetcher.window {
+        true => ppu.window_line as u8,
+        false => ly.wrapping_add(mem.unchecked_read(PpuRegisters::SCY as u16)),
+    };
+
+    match fetcher.step {
+        FetchStep::TileNumber => {
+            let (map_address, x) = match fetcher.window {
+                true => (if lcdc & 0b0100_0000 != 0 { 0x9C00 } else { 0x9800 }, fetcher.tile_x as u16),
+                false => {
+                    let scx = mem.unchecked_read(PpuRegisters::SCX as u16) as u16;
+                    (if lcdc & 0b0000_1000 != 0 { 0x9C00 } else { 0x9800 }, (scx/8 + fetcher.tile_x as u16) % 32)
+                }
+            };
+            let tile_address = map_address + (y as u16 / 8) * 32 + x;
+            fetcher.tile_number = mem.unchecked_read(tile_address);
+            fetcher.step = FetchStep::DataLow;
+        }
+        FetchStep::DataLow => {
+            fetcher.low = mem.unchecked_read(tile_row_address(lcdc, fetcher.tile_number, y));
+            fetcher.step = FetchStep::DataHigh;
+        }
+        FetchStep::DataHigh => {
+            fetcher.high = mem.unchecked_read(tile_row_address(lcdc, fetcher.tile_number, y) + 1);
+            fetcher.step = FetchStep::Push;
+        }
+        FetchStep::Push => {
+            if !ppu.bg_fifo.is_empty() {
+                return;
+            }
+            ppu.bg_fifo.extend(get_individual_pixels(fetcher.low, fetcher.high));
+            fetcher.tile_x = fetcher.tile_x.wrapping_add(1);
+            fetcher.step = FetchStep::TileNumber;
+        }
+    }
+}
 
-    let mut window_pixels: Vec<u8> = vec![BLANK_PIXEL; wx as usize];
+/// where a row of a background or window tile is, depending on the addressing mode
+fn tile_row_address(lcdc: u8, tile_number: u8, y: u8) -> u16 {
+    let tile_address = match lcdc & 0b0001_0000 != 0 {
+        true => 0x8000 + (tile_number as u16) * 16,
+        false => 0x9000_u16.wrapping_add_signed((tile_number as i8 as i16) * 16),
+    };
+    tile_address + (y as u16 % 8) * 2
+}
+
+/// the sprites which start at this pixel get mixed into the object fifo.
+/// sprites hanging off the left of the screen all start at the first pixel.
+/// Gives back how many dots fetching them holds everything up for
+fn load_sprites(ppu: &mut Ppu, mem: &Memory, lcdc: u8) -> u8 {
+    if lcdc & 0b0000_0010 == 0 {
+        return 0;
+    }
+    let x = ppu.line.len();
+    let ly = mem.unchecked_read(PpuRegisters::LY as u16) + 16;
+    // the background tiles line up with scx, the window's with wx
+    let alignment = match ppu.fetcher.window {
+        true => 255 - mem.unchecked_read(PpuRegisters::WX as u16) as usize,
+        false => mem.unchecked_read(PpuRegisters::SCX as u16) as usize,
+    };
+    let obj_size = if lcdc & 0b0000_0100 == 0 { 8 } else { 16 };
+    let starts_here = |sprite: &[u8; 4]| sprite[1] as usize == x + 8 || (x == 0 && sprite[1] < 8);
+
+    let mut stall = 0;
+    for sprite in ppu.sprites.iter().filter(|sprite| starts_here(sprite)) {
+        // each sprite takes 6 dots, plus waiting for the background
+        // fetcher to finish the tile it is on (only for the first on a tile)
+        stall += 6;
+        let tile = (sprite[1] as usize + alignment) / 8;
+        if ppu.stall_tile != Some(tile) {
+            stall += 5 - 5.min((sprite[1] as usize + alignment) % 8) as u8;
+            ppu.stall_tile = Some(tile);
+        }
 
-    // 0 = top layer, 1 = second to top, 2=...
-    let layer_shown = ppu.window_line;
-    ppu.window_line = ppu.window_line + 1;
+        let mut row = (ly - sprite[0]) as u16;
+        if sprite[3] & 0b0100_0000 != 0 {
+            row = obj_size - 1 - row;
+        }
+        let tile_index = if obj_size == 16 { sprite[2] & 0b1111_1110 } else { sprite[2] };
+        let address = 0x8000 + tile_index as u16 * 16 + row * 2;
 
-    let starting_address = (map_address + (layer_shown/8)*32) as u16;
-    let mut tile_number = 0;
-    'window: loop {
-        // dont need to bother with wrapping as windows do not wrap
-        let tile_address = starting_address + tile_number;
-        let tile_data = mem.read_bg_tile(tile_address, addressing);
-        let tile_row_data = tile_data[layer_shown % 8];
+        let mut pixels = get_individual_pixels(mem.unchecked_read(address), mem.unchecked_read(address + 1));
+        if sprite[3] & 0b0010_0000 != 0 {
+            pixels.reverse();
+        }
+        let hidden = 8usize.saturating_sub(sprite[1] as usize);
 
-        let pixels = get_individual_pixels(tile_row_data);
-        for pixel in pixels {
-            window_pixels.push(pixel);
-            if window_pixels.len() == 167 {
-                break 'window;
+        ppu.obj_fifo.resize(8, ObjPixel::default());
+        for (i, color) in pixels.into_iter().skip(hidden).enumerate() {
+            // the sprite that got there first keeps the pixel
+            if ppu.obj_fifo[i].color != 0 {
+                continue;
             }
+            ppu.obj_fifo[i] = ObjPixel {
+                color,
+                palette_1: sprite[3] & 0b0001_0000 != 0,
+                behind_bg: sprite[3] & 0b1000_0000 != 0,
+            };
         }
-        tile_number += 1;
     }
-    return window_pixels[7..].to_vec();
+    ppu.sprites.retain(|sprite| !starts_here(sprite));
+    stall
 }
 
-fn draw_background(mem: &Memory, lcdc: u8, ly: u8) -> Vec<u8> {
-    if lcdc & 0b0000_0001 == 0 {
-        return vec![BLANK_PIXEL; 160];
-    }
-    let mut background_pixels = Vec::new();
-
-    // the address and addressing of the map and subsequent tiles
-    let map_address = if lcdc & 0b0000_1000 != 0 { 0x9C00 } else { 0x9800 };
-    let addressing: u16 = if lcdc & 0b0001_0000 != 0 { 0x8000 } else { 0x8800 };
-
-    // this is the line of the background which we are drawing
-    let background_line = ly.wrapping_add(mem.unchecked_read(PpuRegisters::SCY as u16));
-
-    // which row of tile indexes in the background are used
-    let bg_tile_row = background_line as u16 / 8;
-    let tile_row = background_line as usize % 8;
-
-    let scx = mem.unchecked_read(PpuRegisters::SCX as u16) as u16;
-
-    // drawing all of the tiles
-    let mut tile_number = 0;
-    'background: loop {
-        // get the next tile
-        let tile_index = map_address + (bg_tile_row * 32) + ((scx/8 + tile_number) % 32);
-        let tile = mem.read_bg_tile(tile_index, addressing);
-        let tile_row_data = tile[tile_row];
-        let pixels = get_individual_pixels(tile_row_data);
-        for pixel in pixels {
-            background_pixels.push(pixel);
-            if background_pixels.len() == 168 {
-                break 'background
+/// picks between the background and sprite pixel, using the palettes as they are right now
+fn mix_pixel(mem: &Memory, lcdc: u8, bg_pixel: u8, obj_pixel: Option<ObjPixel>) -> u8 {
+    let bg_pixel = if lcdc & 0b0000_0001 == 0 { BLANK_PIXEL } else { bg_pixel };
+    let bg_palette = mem.unchecked_read(PpuRegisters::BGP as u16);
+
+    match obj_pixel {
+        Some(obj) if lcdc & 0b0000_0010 != 0 && obj.color != 0 => {
+            // 1-3 of the background draws over sprites with the priority bit
+            if obj.behind_bg && !matches!(bg_pixel, 0 | BLANK_PIXEL) {
+                return to_palette(bg_pixel, bg_palette);
             }
+            let palette = match obj.palette_1 {
+                true => PpuRegisters::OBP1,
+                false => PpuRegisters::OBP0,
+            };
+            to_palette(obj.color, mem.unchecked_read(palette as u16))
         }
-        tile_number += 1;
+        _ => to_palette(bg_pixel, bg_palette),
     }
-    return background_pixels[((scx%8) as usize)..].to_vec();
 }
 
-fn get_individual_pixels(tile_row: u16) -> Vec<u8> {
-    let mut pixels = Vec::new();
-    for i in (0..8).rev() {
-        pixels.push((tile_row >> (i*2) & 3) as u8);
+fn get_individual_pixels(low: u8, high: u8) -> [u8; 8] {
+    let mut pixels = [0; 8];
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let bit = 7 - i;
+        *pixel = (low >> bit & 1) | (high >> bit & 1) << 1;
     }
-    return pixels
-}
\ No newline at end of file
+    pixels
+}
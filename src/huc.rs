@@ -0,0 +1,325 @@
+use crate::mbc::{banked_ram_address, load_into, read_banked_rom, rom_bank_number, MBC};
+use crate::rtc::{load_clock, save_with_clock};
+
+/// how many T-cycles make up one minute, the huc3 clock only counts minutes
+const CYCLES_PER_MINUTE: usize = 4_194_304 * 60;
+
+/// how many bytes the huc3 clock takes up in a save
+const CLOCK_SIZE: usize = 0x104;
+
+/// what the ir port reads as when there is no other gameboy to talk to
+const IR_NO_LIGHT: u8 = 0xC0;
+
+/// hudson's first mapper, close to an mbc1 apart from the infrared port
+pub struct HuC1 {
+    rom: Vec<u8>,
+    rom_index: usize,
+    total_rom_banks: usize,
+
+    ram: Vec<u8>,
+    ram_index: usize,
+    // 0x0E written to 0x0000-0x1FFF swaps the ram out for the ir port
+    ir_mode: bool,
+}
+impl HuC1 {
+    pub fn new(rom: Vec<u8>, total_rom_banks: usize, ram: Vec<u8>) -> Self {
+        Self { rom, rom_index: 1, total_rom_banks, ram, ram_index: 0, ir_mode: false }
+    }
+    fn ram_address(&self, address: usize) -> usize {
+        banked_ram_address(&self.ram, self.ram_index, address)
+    }
+}
+impl MBC for HuC1 {
+    fn read_rom(&self, address: usize) -> u8 {
+        read_banked_rom(&self.rom, self.rom_index % self.total_rom_banks, address)
+    }
+    fn write_rom(&mut self, address: usize, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ir_mode = data & 0x0F == 0x0E,
+            0x2000..=0x3FFF => self.rom_index = rom_bank_number(data, 0x3F),
+            0x4000..=0x5FFF => self.ram_index = data as usize & 0x03,
+            _ => {}
+        }
+    }
+    fn read_ram(&self, address: usize) -> u8 {
+        if self.ir_mode {
+            return IR_NO_LIGHT;
+        }
+        if self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[self.ram_address(address)]
+    }
+    fn write_ram(&mut self, address: usize, data: u8) {
+        // the led being turned on and off doesnt matter with nobody watching
+        if self.ir_mode || self.ram.is_empty() {
+            return;
+        }
+        let ram_address = self.ram_address(address);
+        self.ram[ram_address] = data;
+    }
+    fn save_ram(&self) -> Option<Vec<u8>> {
+        Some(self.ram.clone())
+    }
+    fn load_ram(&mut self, data: &[u8]) {
+        load_into(&mut self.ram, data);
+    }
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+}
+
+/// hudson's later mapper, the register at 0x0000-0x1FFF picks what
+/// shows up at 0xA000-0xBFFF instead of just enabling the ram
+#[derive(Clone, Copy, PartialEq)]
+enum HuC3Mode {
+    RamReadOnly,
+    Ram,
+    RtcCommand,
+    RtcResponse,
+    RtcSemaphore,
+    Ir,
+    Nothing,
+}
+
+pub struct HuC3 {
+    rom: Vec<u8>,
+    rom_index: usize,
+    total_rom_banks: usize,
+
+    ram: Vec<u8>,
+    ram_index: usize,
+    mode: HuC3Mode,
+
+    clock: HuC3Clock,
+}
+impl HuC3 {
+    pub fn new(rom: Vec<u8>, total_rom_banks: usize, ram: Vec<u8>) -> Self {
+        Self {
+            rom,
+            rom_index: 1,
+            total_rom_banks,
+            ram,
+            ram_index: 0,
+            mode: HuC3Mode::Nothing,
+            clock: HuC3Clock::new(),
+        }
+    }
+    fn ram_address(&self, address: usize) -> usize {
+        banked_ram_address(&self.ram, self.ram_index, address)
+    }
+}
+impl MBC for HuC3 {
+    fn read_rom(&self, address: usize) -> u8 {
+        read_banked_rom(&self.rom, self.rom_index % self.total_rom_banks, address)
+    }
+    fn write_rom(&mut self, address: usize, data: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                self.mode = match data & 0x0F {
+                    0x0 => HuC3Mode::RamReadOnly,
+                    0xA => HuC3Mode::Ram,
+                    0xB => HuC3Mode::RtcCommand,
+                    0xC => HuC3Mode::RtcResponse,
+                    0xD => HuC3Mode::RtcSemaphore,
+                    0xE => HuC3Mode::Ir,
+                    _ => HuC3Mode::Nothing,
+                }
+            }
+            0x2000..=0x3FFF => self.rom_index = data as usize & 0x7F,
+            0x4000..=0x5FFF => self.ram_index = data as usize & 0x03,
+            _ => {}
+        }
+    }
+    fn read_ram(&self, address: usize) -> u8 {
+        match self.mode {
+            HuC3Mode::RamReadOnly | HuC3Mode::Ram if !self.ram.is_empty() => {
+                self.ram[self.ram_address(address)]
+            }
+            HuC3Mode::RtcCommand | HuC3Mode::RtcResponse => self.clock.response,
+            // commands finish straight away so the clock is always ready
+            HuC3Mode::RtcSemaphore => 0xFF,
+            HuC3Mode::Ir => IR_NO_LIGHT,
+            _ => 0xFF,
+        }
+    }
+    fn write_ram(&mut self, address: usize, data: u8) {
+        match self.mode {
+            HuC3Mode::Ram if !self.ram.is_empty() => {
+                let ram_address = self.ram_address(address);
+                self.ram[ram_address] = data;
+            }
+            HuC3Mode::RtcCommand => self.clock.command = data & 0x7F,
+            // clearing bit 0 runs the command
+            HuC3Mode::RtcSemaphore if data & 1 == 0 => self.clock.execute(),
+            _ => {}
+        }
+    }
+    fn tick(&mut self, cycles: u8) {
+        self.clock.tick(cycles);
+    }
+    fn save_ram(&self) -> Option<Vec<u8>> {
+        Some(save_with_clock(&self.ram, &self.clock.save()))
+    }
+    fn load_ram(&mut self, data: &[u8]) {
+        load_into(&mut self.ram, data);
+        if let Some((clock, elapsed)) = load_clock(data, self.ram.len(), CLOCK_SIZE) {
+            self.clock.load(clock, elapsed);
+        }
+    }
+    fn save_has_timestamp(&self) -> bool {
+        true
+    }
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+}
+
+/// the huc3 clock is a separate chip with 256 nibbles of memory. The game
+/// sends it a command nibble and an argument nibble and reads back a response.
+/// the commands are from https://gbdev.io/pandocs/HuC3.html
+struct HuC3Clock {
+    // minutes into the day and the days since the clock was set
+    minutes: u16,
+    days: u16,
+    cycles: usize,
+
+    memory: [u8; 0x100],
+    address: u8,
+    command: u8,
+    response: u8,
+}
+impl HuC3Clock {
+    fn new() -> Self {
+        Self {
+            minutes: 0,
+            days: 0,
+            cycles: 0,
+            memory: [0; 0x100],
+            address: 0,
+            command: 0,
+            response: 0x80,
+        }
+    }
+
+    fn tick(&mut self, cycles: u8) {
+        self.cycles += cycles as usize;
+        while self.cycles >= CYCLES_PER_MINUTE {
+            self.cycles -= CYCLES_PER_MINUTE;
+            self.advance(1);
+        }
+    }
+
+    fn advance(&mut self, minutes: u64) {
+        let total = self.minutes as u64 + minutes;
+        self.minutes = (total % 1440) as u16;
+        self.days = ((self.days as u64 + total / 1440) & 0xFFF) as u16;
+    }
+
+    fn execute(&mut self) {
+        let argument = self.command & 0x0F;
+        let mut result = 0;
+        match self.command >> 4 {
+            // read a nibble, moving onto the next one
+            0x1 => {
+                result = self.memory[self.address as usize];
+                self.address = self.address.wrapping_add(1);
+            }
+            // write a nibble, moving onto the next one
+            0x3 => {
+                self.memory[self.address as usize] = argument;
+                self.address = self.address.wrapping_add(1);
+            }
+            0x4 => self.address = (self.address & 0xF0) | argument,
+            0x5 => self.address = (self.address & 0x0F) | argument << 4,
+            0x6 => match argument {
+                // copy the time into memory, 3 nibbles each for minutes and days
+                0x0 => {
+                    for i in 0..3 {
+                        self.memory[i] = (self.minutes >> (i * 4)) as u8 & 0x0F;
+                        self.memory[3 + i] = (self.days >> (i * 4)) as u8 & 0x0F;
+                    }
+                }
+                // set the time from memory
+                0x1 => {
+                    self.minutes = 0;
+                    self.days = 0;
+                    for i in 0..3 {
+                        self.minutes |= (self.memory[i] as u16) << (i * 4);
+                        self.days |= (self.memory[3 + i] as u16) << (i * 4);
+                    }
+                    self.minutes %= 1440;
+                    self.cycles = 0;
+                }
+                // the status check, games want a 1 back
+                0x2 => result = 0x1,
+                // everything else is the speaker
+                _ => {}
+            },
+            _ => {}
+        }
+        self.response = 0x80 | self.command & 0x70 | result;
+    }
+
+    /// the clock memory then the minutes and days as 16 bit little endian values
+    fn save(&self) -> Vec<u8> {
+        let mut data = self.memory.to_vec();
+        data.extend_from_slice(&self.minutes.to_le_bytes());
+        data.extend_from_slice(&self.days.to_le_bytes());
+        data
+    }
+
+    /// loads the clock and then adds on however long it has been since the save
+    fn load(&mut self, data: &[u8], elapsed: u64) {
+        self.memory.copy_from_slice(&data[..0x100]);
+        self.minutes = u16::from_le_bytes([data[0x100], data[0x101]]) % 1440;
+        self.days = u16::from_le_bytes([data[0x102], data[0x103]]) & 0xFFF;
+        self.advance(elapsed / 60);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc::{unix_time, TIMESTAMP_SIZE};
+
+    /// sends the clock a command the way a game does
+    fn command(huc3: &mut HuC3, command: u8) {
+        huc3.write_rom(0x0000, 0x0B);
+        huc3.write_ram(0xA000, command);
+        huc3.write_rom(0x0000, 0x0D);
+        huc3.write_ram(0xA000, 0xFE);
+        huc3.write_rom(0x0000, 0x0C);
+    }
+
+    #[test]
+    fn clock_catches_up_after_load() {
+        let mut huc3 = HuC3::new(vec![0; 0x8000], 2, vec![0; 0x2000]);
+        // 23:50 on day 3, written into the clock's memory then set
+        command(&mut huc3, 0x40);
+        command(&mut huc3, 0x50);
+        for nibble in [0x6, 0x9, 0x5, 0x3, 0x0, 0x0] {
+            command(&mut huc3, 0x30 | nibble);
+        }
+        command(&mut huc3, 0x61);
+
+        // saved 20 minutes ago
+        let mut save = huc3.save_ram().unwrap();
+        let timestamp = save.len() - TIMESTAMP_SIZE;
+        save.truncate(timestamp);
+        save.extend((unix_time() - 20 * 60).to_le_bytes());
+
+        let mut loaded = HuC3::new(vec![0; 0x8000], 2, vec![0; 0x2000]);
+        loaded.load_ram(&save);
+        command(&mut loaded, 0x60);
+        command(&mut loaded, 0x40);
+        command(&mut loaded, 0x50);
+        let mut nibbles = Vec::new();
+        for _ in 0..6 {
+            command(&mut loaded, 0x10);
+            nibbles.push(loaded.read_ram(0xA000) & 0x0F);
+        }
+        // 00:10 on day 4
+        assert_eq!(nibbles, [0xA, 0x0, 0x0, 0x4, 0x0, 0x0]);
+    }
+}
@@ -0,0 +1,76 @@
+/// the bits of every io register which are unused and always read back as 1.
+/// registers which dont exist at all on the dmg have every bit set so they read 0xFF
+const IO_READ_MASKS: [u8; 0x80] = build_read_masks();
+
+const fn build_read_masks() -> [u8; 0x80] {
+    let mut masks = [0xFF; 0x80];
+
+    masks[0x00] = 0xC0; // P1
+    masks[0x01] = 0x00; // SB
+    masks[0x02] = 0x7E; // SC
+    masks[0x04] = 0x00; // DIV
+    masks[0x05] = 0x00; // TIMA
+    masks[0x06] = 0x00; // TMA
+    masks[0x07] = 0xF8; // TAC
+    masks[0x0F] = 0xE0; // IF
+
+    // sound registers, the write only ones are left as 0xFF
+    masks[0x10] = 0x80; // NR10
+    masks[0x11] = 0x3F; // NR11
+    masks[0x12] = 0x00; // NR12
+    masks[0x14] = 0xBF; // NR14
+    masks[0x16] = 0x3F; // NR21
+    masks[0x17] = 0x00; // NR22
+    masks[0x19] = 0xBF; // NR24
+    masks[0x1A] = 0x7F; // NR30
+    masks[0x1C] = 0x9F; // NR32
+    masks[0x1E] = 0xBF; // NR34
+    masks[0x21] = 0x00; // NR42
+    masks[0x22] = 0x00; // NR43
+    masks[0x23] = 0xBF; // NR44
+    masks[0x24] = 0x00; // NR50
+    masks[0x25] = 0x00; // NR51
+    masks[0x26] = 0x70; // NR52
+
+    // wave ram
+    let mut i = 0x30;
+    while i < 0x40 {
+        masks[i] = 0x00;
+        i += 1;
+    }
+
+    // lcd registers
+    let mut i = 0x40;
+    while i < 0x4C {
+        masks[i] = 0x00;
+        i += 1;
+    }
+    masks[0x41] = 0x80; // STAT
+
+    masks
+}
+
+/// the io registers from 0xFF00 to 0xFF7F. They store whatever gets written
+/// to them but reads go through the read masks like on the real hardware
+pub struct IoRegisters {
+    regs: [u8; 0x80],
+}
+impl IoRegisters {
+    pub fn new() -> Self {
+        Self { regs: [0; 0x80] }
+    }
+
+    /// what the cpu would see when reading the register
+    pub fn read(&self, address: u16) -> u8 {
+        let index = (address - 0xFF00) as usize;
+        self.regs[index] | IO_READ_MASKS[index]
+    }
+
+    /// the raw value stored in the register, unused bits included
+    pub fn get(&self, address: u16) -> u8 {
+        self.regs[(address - 0xFF00) as usize]
+    }
+    pub fn set(&mut self, address: u16, data: u8) {
+        self.regs[(address - 0xFF00) as usize] = data;
+    }
+}
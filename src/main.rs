@@ -6,20 +6,63 @@ mod gpu;
 mod util;
 mod args;
 mod mbc;
+mod rtc;
+mod eeprom;
+mod huc;
+mod camera;
+mod patch;
+mod archive;
+mod cheats;
+mod search;
+mod console;
+mod watch;
+mod save;
+mod model;
+mod io;
+mod cartridge;
 
-use cpu::Cpu;
+use cpu::{Cpu, Registers};
 use gpu::{Ppu, update_ppu};
-use memory::{Memory, update_timer};
+use memory::{Memory, RamFiller, update_timer, update_dma, update_cartridge};
 use processor::{run, handle_interrupts};
 use util::INTERRUPT_F_ADDRESS;
+use mbc::CartridgeEvent;
+use save::{SaveFile, SAVE_INTERVAL};
 
+use args::{Args, Command};
+use model::Model;
+use archive::RomFile;
+use cheats::{load_cheats, Cheat};
+use console::Console;
+use watch::WatchList;
+use cartridge::get_rom;
+use camera::{load_camera_frame, FRAMES_PER_PICTURE};
 use clap::Parser;
+use std::path::PathBuf;
 use macroquad::prelude::*;
 
+const CHEAT_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5,
+    KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+];
+
 /// this function is useful for debugging purposes
 /// any keybindings which I want to use will be done in this function
-fn misc_inputs(_mem: &Memory) {
+fn misc_inputs(mem: &mut Memory) {
+    // the number keys turn the first 9 cheats on and off
+    for (i, key) in CHEAT_KEYS.iter().enumerate() {
+        if !is_key_pressed(*key) {
+            continue;
+        }
+        if let Some(cheat) = mem.toggle_cheat(i) {
+            print_cheat(i, cheat);
+        }
+    }
+}
 
+fn print_cheat(index: usize, cheat: &Cheat) {
+    let state = if cheat.enabled { "on" } else { "off" };
+    println!("cheat {} {} {} is {state}", index + 1, cheat.code, cheat.name);
 }
 
 pub fn joypad_interrupt(mem: &mut Memory) {
@@ -59,8 +102,37 @@ pub fn joypad(joypad: u8) -> u8 {
     return upper_joypad;
 }
 
+/// how the cartridge is being tilted, for the ones with an accelerometer.
+/// the arrow keys tilt it all the way, or holding the mouse down tilts it
+/// towards wherever the mouse is on the screen
+fn tilt_input() -> (f32, f32) {
+    if is_mouse_button_down(MouseButton::Left) {
+        let (x, y) = mouse_position();
+        let x = x / screen_width() * 2.0 - 1.0;
+        let y = y / screen_height() * 2.0 - 1.0;
+        return (x, y);
+    }
+    let x = is_key_down(KeyCode::Right) as i8 - is_key_down(KeyCode::Left) as i8;
+    let y = is_key_down(KeyCode::Down) as i8 - is_key_down(KeyCode::Up) as i8;
+    (x as f32, y as f32)
+}
+
+/// the watched values in the top left, over the game
+fn draw_watches(watches: &WatchList, mem: &Memory) {
+    let font_size = 4.0 * SCALE_FACTOR as f32;
+    for (i, watch) in watches.watches().iter().enumerate() {
+        let value = watch.value(mem).unwrap_or_else(|| "-".to_string());
+        let text = format!("{}: {value}", watch.name);
+        let y = (i + 1) as f32 * font_size;
+        let size = measure_text(&text, None, font_size as u16, 1.0);
+        draw_rectangle(0.0, y - font_size * 0.8, size.width + 4.0, font_size, Color::new(0.0, 0.0, 0.0, 0.6));
+        draw_text(&text, 2.0, y, font_size, WHITE);
+    }
+}
+
 fn serial_output(mem: &mut Memory) {
-    if mem.read(0xFF02) == 0x81 {
+    // the unused bits of SC always read as 1
+    if mem.read(0xFF02) & 0x81 == 0x81 {
         let c = mem.read(0xFF01) as char;
         print!("{c}");
         mem.write(0xFF02, 0);
@@ -78,11 +150,27 @@ fn to_screen_pixel(p: u8) -> Color {
     }
 }
 
-fn get_rom(rom_path: &String) -> Vec<u8> {
-    match std::fs::read(rom_path) {
-        Err(e) => panic!("invalid file provided => {e:?}"),
-        Ok(f) => f,
+/// the dmg boot roms are 256 bytes, the colour ones are 2304
+fn get_boot_rom(boot_rom_path: &String, model: Model) -> Vec<u8> {
+    let boot_rom = std::fs::read(boot_rom_path)
+        .unwrap_or_else(|e| exit_with_error(format!("could not read the boot rom: {e}")));
+    let (expected, kind) = match model.is_cgb() {
+        true => (0x900, "colour"),
+        false => (0x100, "dmg"),
+    };
+    if boot_rom.len() != expected {
+        exit_with_error(format!(
+            "this model needs a {expected} byte {kind} boot rom, the one given is {} bytes",
+            boot_rom.len(),
+        ));
     }
+    boot_rom
+}
+
+/// a bad rom is the user's problem not a bug, so no backtrace
+fn exit_with_error(error: impl std::fmt::Display) -> ! {
+    eprintln!("error: {error}");
+    std::process::exit(1);
 }
 
 const SCALE_FACTOR: i32 = 4;
@@ -96,57 +184,143 @@ fn window_conf() -> Conf {
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
+fn main() {
     let args = args::Args::parse();
-    let rom = get_rom(&args.rom_name);
+    if let Some(Command::Info { rom_name }) = &args.command {
+        let rom = get_rom(rom_name, None).unwrap_or_else(|e| exit_with_error(e));
+        cartridge::print_info(&rom.data);
+        return;
+    }
+
+    // everything that can go wrong with the rom is checked before the window opens
+    // clap makes sure there is a rom when there is no subcommand
+    let rom_name = args.rom_name.clone().unwrap();
+    let RomFile { data: rom, base_path } = get_rom(&rom_name, args.patch.as_ref())
+        .unwrap_or_else(|e| exit_with_error(e));
+    let boot_rom = args.boot_rom.as_ref().map(|path| get_boot_rom(path, args.model));
+
+    let cpu = match boot_rom.is_none() {
+        true => Cpu::new(args.model.registers(&rom)),
+        false => Cpu::new(Registers::default()),
+    };
+    let mut ram = RamFiller::new(args.ram_init, args.seed);
+    let memory = Memory::new(rom, boot_rom, args.model, &mut ram)
+        .unwrap_or_else(|e| exit_with_error(e));
 
-    let mut cpu = Cpu::new(args.booted);
-    let mut memory = Memory::new(rom, args.booted);
+    let camera_frames: Vec<Vec<u8>> = args.camera.iter()
+        .map(|path| load_camera_frame(path).unwrap_or_else(|e| exit_with_error(e)))
+        .collect();
 
-    let mut ppu = Ppu::default();
+    macroquad::Window::from_config(window_conf(), emulate(args, base_path, cpu, memory, camera_frames));
+}
+
+async fn emulate(args: Args, base_path: PathBuf, mut cpu: Cpu, mut memory: Memory, camera_frames: Vec<Vec<u8>>) {
+    let booted = args.boot_rom.is_none();
+
+    for cheat in load_cheats(&base_path) {
+        memory.add_cheat(cheat);
+    }
+    for (i, cheat) in memory.cheats().iter().enumerate() {
+        print_cheat(i, cheat);
+    }
 
+    let mut save_file = args.save.then(|| SaveFile::new(&base_path));
+    if let Some(save_file) = &mut save_file {
+        save_file.load(&mut memory);
+    }
+    // the save has to be written before the window closes
+    prevent_quit();
+
+    let mut ppu = match booted {
+        true => {
+            let (ly, dot) = args.model.ppu_position();
+            Ppu::at_position(&mut memory, ly, dot)
+        }
+        false => Ppu::default(),
+    };
+
+    let mut console = Console::new();
+    let mut watches = WatchList::load(&base_path);
+    let mut show_watches = true;
     let mut pixel_buffer: Vec<u8> = Vec::new();
-    'full: loop {
+    let mut rumbling = false;
+    let mut frame_count: u64 = 0;
+    loop {
+        if is_quit_requested() {
+            if let Some(save_file) = &mut save_file {
+                save_file.write(&memory);
+            }
+            break;
+        }
         joypad_interrupt(&mut memory);
+        let (tilt_x, tilt_y) = tilt_input();
+        memory.set_tilt(tilt_x, tilt_y);
+        if !camera_frames.is_empty() && frame_count.is_multiple_of(FRAMES_PER_PICTURE) {
+            let picture = (frame_count / FRAMES_PER_PICTURE) as usize % camera_frames.len();
+            memory.set_camera_frame(&camera_frames[picture]);
+        }
 
         while pixel_buffer.len() != 23040 {
-            // fail-safe for the boot rom
-            if cpu.regs.pc == 0x100 && !args.booted {
-                break 'full;
-            }
-
+            memory.begin_step();
             let mut cycles = 4;
             cycles += handle_interrupts(&mut cpu, &mut memory);
             if !cpu.halt {
                 // have to account for the previous 4 cycles 
                 cycles += run(&mut cpu, &mut memory) - 4;
             }
+            memory.end_step();
             update_timer(&mut memory, cycles);
+            update_dma(&mut memory, cycles);
+            update_cartridge(&mut memory, cycles);
             serial_output(&mut memory);
 
             if let Some(line) = update_ppu(&mut ppu, &mut memory, cycles) {
                 pixel_buffer.extend::<Vec<u8>>(line);
             }
         }
-        misc_inputs(&memory);
+        memory.apply_cheats();
+        misc_inputs(&mut memory);
+        console.update(&mut memory, &mut watches);
+        if is_key_pressed(KeyCode::Tab) {
+            show_watches = !show_watches;
+        }
+        while let Some(event) = memory.poll_cartridge_event() {
+            match event {
+                CartridgeEvent::Rumble(on) => rumbling = on,
+            }
+        }
+        frame_count += 1;
+        if frame_count.is_multiple_of(SAVE_INTERVAL) {
+            if let Some(save_file) = &mut save_file {
+                save_file.write(&memory);
+            }
+        }
+
+        // the screen gets shaken side to side while the rumble motor is on
+        let shake = match rumbling {
+            true if frame_count.is_multiple_of(2) => SCALE_FACTOR as f32 / 2.0,
+            true => -(SCALE_FACTOR as f32 / 2.0),
+            false => 0.0,
+        };
+        if rumbling {
+            clear_background(BLACK);
+        }
+
         // all of the actual rendering to the screen
         for (j, pixel) in pixel_buffer.iter().enumerate() {
             let pixel = to_screen_pixel(*pixel);
             draw_rectangle(
-                ((j%160)*SCALE_FACTOR as usize) as f32, //pos
+                ((j%160)*SCALE_FACTOR as usize) as f32 + shake, //pos
                 ((j/160)*SCALE_FACTOR as usize) as f32, //pos
                 SCALE_FACTOR as f32, //width
                 SCALE_FACTOR as f32, //height
                 pixel // color
             );
         }
+        if show_watches {
+            draw_watches(&watches, &memory);
+        }
         next_frame().await;
         pixel_buffer.clear();
-
-        // debug section of the emulator
-        if !args.booted && cpu.regs.pc == 0xE9 {
-            break 'full;
-        }
     }
 }
\ No newline at end of file
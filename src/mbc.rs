@@ -1,106 +1,151 @@
+use crate::memory::RamFiller;
+use crate::camera::PocketCamera;
+use crate::cartridge::{header_checksum, CartridgeError, Header};
+use crate::eeprom::Eeprom;
+use crate::huc::{HuC1, HuC3};
+use crate::rtc::{load_clock, save_with_clock, Rtc, CLOCK_SIZE};
+use crate::util::NINTENDO_LOGO;
+
+/// things a cartridge does which the frontend might want to show
+#[derive(Debug, Clone, Copy)]
+pub enum CartridgeEvent {
+    Rumble(bool),
+}
+
 pub trait MBC {
     fn read_rom(&self, address: usize) -> u8;
     fn write_rom(&mut self, address: usize, data: u8);
 
     fn read_ram(&self, address: usize) -> u8;
     fn write_ram(&mut self, address: usize, data: u8);
+
+    /// takes the latest event the cartridge produced, if there is one
+    fn poll_event(&mut self) -> Option<CartridgeEvent> {
+        None
+    }
+
+    /// for anything on the cartridge which keeps time
+    fn tick(&mut self, _cycles: u8) {}
+
+    /// everything that should go into the save file, none if nothing is kept
+    fn save_ram(&self) -> Option<Vec<u8>> {
+        None
+    }
+    fn load_ram(&mut self, _data: &[u8]) {}
+    /// whether the save ends in the timestamp from save_with_clock
+    fn save_has_timestamp(&self) -> bool {
+        false
+    }
+
+    /// for the cartridges with an accelerometer, each axis goes from -1 to 1
+    fn set_tilt(&mut self, _x: f32, _y: f32) {}
+
+    /// for the camera, a greyscale picture of whatever it is pointed at
+    fn set_camera_frame(&mut self, _frame: &[u8]) {}
+
+    /// the ram bank currently mapped to 0xA000-0xBFFF
+    fn ram_bank(&self) -> usize {
+        0
+    }
+
+    /// all of the cartridge ram, every bank, whether it is enabled or not
+    fn ram(&self) -> &[u8] {
+        &[]
+    }
 }
 
 pub struct MBC1 {
     rom_banks: Vec<u8>,
-    high_bank_index: usize,
-    zero_bank_index: usize,
+    total_rom_banks: usize,
 
-    ram: Vec<u8>,
-    ram_index: usize,
+    // the 5 bit register at 0x2000-0x3FFF
+    low_bank: usize,
+    // the 2 bit register at 0x4000-0x5FFF, it is either the
+    // upper bits of the rom bank or the ram bank
+    high_bank: usize,
 
+    ram: Vec<u8>,
     ram_enabled: bool,
     mode: bool,
+    battery: bool,
 
-    total_rom_banks: usize,
+    // the MBC1M board wires the upper bits one lower, so bit 4
+    // of the low register is ignored and each game gets 16 banks
+    multicart: bool,
+}
+impl MBC1 {
+    fn bank_shift(&self) -> usize {
+        if self.multicart { 4 } else { 5 }
+    }
+    fn zero_bank_index(&self) -> usize {
+        if !self.mode {
+            return 0;
+        }
+        (self.high_bank << self.bank_shift()) % self.total_rom_banks
+    }
+    fn high_bank_index(&self) -> usize {
+        let low_mask = (1 << self.bank_shift()) - 1;
+        ((self.high_bank << self.bank_shift()) | (self.low_bank & low_mask)) % self.total_rom_banks
+    }
+    /// the ram can only be banked in the second mode
+    fn ram_address(&self, address: usize) -> usize {
+        let bank = if self.mode { self.high_bank } else { 0 };
+        (bank * 0x2000 + (address - 0xA000)) % self.ram.len()
+    }
 }
 impl MBC for MBC1 {
     fn read_rom(&self, address: usize) -> u8 { 
         if address < 0x4000 {
-            return self.rom_banks[address + self.zero_bank_index * 0x4000];
+            return self.rom_banks[address + self.zero_bank_index() * 0x4000];
         }
-        let offset_address = (0x4000 * self.high_bank_index) + (address % 0x4000);
+        let offset_address = (0x4000 * self.high_bank_index()) + (address % 0x4000);
         self.rom_banks[offset_address]
     }
     fn write_rom(&mut self, address: usize, data: u8) {
         match address {
-            0..=0x1FFF => self.ram_enabled = data & 0xA == 0xA,
-            0x2000..=0x3FFF => {
-                if data == 0 || self.total_rom_banks == 2 {
-                    self.high_bank_index = 1;
-                    return;
-                }
-                let bitmask = match self.total_rom_banks {
-                    128 | 64 | 32 => 0b0001_1111,
-                    16 => 0b0000_1111,
-                    8 => 0b0000_0111,
-                    4 => 0b0000_0011,
-                    _ => unreachable!(),
-                };
-                let rom_bank = data & bitmask;
-                self.high_bank_index = rom_bank as usize;
-                if self.total_rom_banks == 64 {
-                    let lower_ram_bit = self.ram_index & 1;
-                    self.high_bank_index |= lower_ram_bit << 5;
-                }
-                if self.total_rom_banks == 128 {
-                    self.high_bank_index |= self.ram_index << 5;
-                }
-            }
-            0x4000..=0x5FFF => {
-                let ram_bank = data & 0b0000_0011;
-                self.ram_index = ram_bank as usize;
-            }
-            0x6000..=0x7FFF => {
-                let mode = data & 1 == 1;
-                self.mode = mode;
-
-
-                if self.total_rom_banks <= 32 {
-                    return;
-                }
-                if self.total_rom_banks == 64 {
-                    let lower_ram_bit = self.ram_index & 1;
-                    self.zero_bank_index &= 0;
-                    self.zero_bank_index |= lower_ram_bit<<5;
-                    return;
-                }
-                if self.total_rom_banks == 128 {
-                    self.zero_bank_index &= 0;
-                    self.zero_bank_index |= self.ram_index << 6;
-                }
-            }
+            0..=0x1FFF => self.ram_enabled = data & 0x0F == 0x0A,
+            // a 0 here gets turned into a 1, even if the upper bits are set
+            0x2000..=0x3FFF => self.low_bank = (data as usize & 0x1F).max(1),
+            0x4000..=0x5FFF => self.high_bank = data as usize & 0b0000_0011,
+            0x6000..=0x7FFF => self.mode = data & 1 == 1,
             _ => {}
         }
     }
     fn read_ram(&self, address: usize) -> u8 {
-        if !self.ram_enabled {
-            eprintln!("read to ram was ignored");
+        if !self.ram_enabled || self.ram.is_empty() {
             return 0xFF;
         }
-        let offset_address = address - 0xA000;
-        self.ram[self.ram_index * 0x2000 + offset_address]
+        self.ram[self.ram_address(address)]
     }
     fn write_ram(&mut self, address: usize, data: u8) {
-        if !self.ram_enabled {
-            eprintln!("write to ram was ignored");
+        if !self.ram_enabled || self.ram.is_empty() {
             return;
         }
-        let offset_address = address - 0xA000;
-        self.ram[self.ram_index * 0x2000 + offset_address] = data;
+        let ram_address = self.ram_address(address);
+        self.ram[ram_address] = data;
+    }
+    fn ram_bank(&self) -> usize {
+        if self.mode { self.high_bank } else { 0 }
+    }
+    fn save_ram(&self) -> Option<Vec<u8>> {
+        self.battery.then(|| self.ram.clone())
+    }
+    fn load_ram(&mut self, data: &[u8]) {
+        load_into(&mut self.ram, data);
+    }
+    fn ram(&self) -> &[u8] {
+        &self.ram
     }
 }
 pub struct MBC2 {
     rom_banks: Vec<u8>,
     high_rom_index: usize,
+    total_rom_banks: usize,
     
     ram_enabled: bool,
-    ram: Vec<u8>
+    // 512 half bytes built into the mbc itself
+    ram: Vec<u8>,
+    battery: bool,
 }
 impl MBC for MBC2 {
     fn read_rom(&self, address: usize) -> u8 {
@@ -111,32 +156,58 @@ impl MBC for MBC2 {
         return self.rom_banks[rom_address];
     }
     fn write_rom(&mut self, address: usize, data: u8) {
+        // both registers are in the lower half, bit 8 of the address picks which
+        if address > 0x3FFF {
+            return;
+        }
         if address & 0x100 == 0 {
-            self.ram_enabled = data & 0xA == 0xA;
+            self.ram_enabled = data & 0x0F == 0x0A;
             return;
         }
-        self.high_rom_index = data.min(1) as usize;
+        self.high_rom_index = (data as usize & 0x0F).max(1) % self.total_rom_banks;
     }
     fn read_ram(&self, address: usize) -> u8 {
-        // it might be pointless to reconfirm it is 4 bits as all the
-        // writes already do it but there is no harm in doing it
-        return self.ram[address&0x1FF] & 0x0F
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        // only the lower 4 bits exist, the upper ones just read as 1
+        return self.ram[address&0x1FF] | 0xF0
     }
     fn write_ram(&mut self, address: usize, data: u8) {
         if !self.ram_enabled {
-            eprintln!("the write is ignored");
+            return;
         }
         let actual_address = address & 0x1FF;
         self.ram[actual_address] = data & 0x0F;
     }
+    fn save_ram(&self) -> Option<Vec<u8>> {
+        self.battery.then(|| self.ram.clone())
+    }
+    fn load_ram(&mut self, data: &[u8]) {
+        load_into(&mut self.ram, data);
+    }
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
 }
 struct MBC3 {
     rom: Vec<u8>,
     high_rom_index: usize,
 
     ram: Vec<u8>,
+    // 0x00-0x03 select a ram bank, 0x08-0x0C select a clock register
     ram_index: usize,
     ram_enabled: bool,
+
+    rtc: Option<Rtc>,
+    battery: bool,
+    total_rom_banks: usize,
+}
+impl MBC3 {
+    /// banks past what the cartridge has just wrap around
+    fn ram_address(&self, address: usize) -> usize {
+        banked_ram_address(&self.ram, self.ram_index, address)
+    }
 }
 impl MBC for MBC3 {
     fn read_rom(&self, address: usize) -> u8 {
@@ -147,99 +218,633 @@ impl MBC for MBC3 {
         return self.rom[actual_address];
     }
     fn read_ram(&self, address: usize) -> u8 {
-        let actual_address = (0x2000 * self.ram_index) + (address - 0xA000);
-        return self.ram[actual_address];
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        match (self.ram_index, &self.rtc) {
+            (0x08..=0x0C, Some(rtc)) => rtc.read(self.ram_index),
+            (0x00..=0x07, _) if !self.ram.is_empty() => self.ram[self.ram_address(address)],
+            _ => 0xFF,
+        }
     }
     fn write_rom(&mut self, address: usize, data: u8) {
         match address {
             0..=0x1FFF => {
-                self.ram_enabled = data & 0xA == 0xA;
+                self.ram_enabled = data & 0x0F == 0x0A;
             }
             0x2000..=0x3FFF => {
-                self.high_rom_index = (data & 0x7F).min(1) as usize;
+                self.high_rom_index = (data as usize & 0x7F).max(1) % self.total_rom_banks;
             }
             0x4000..=0x5FFF => {
-                if data > 0x04 {
-                    panic!("havent implemened RTC yet");
-                }
                 self.ram_index = data as usize;
             }
             0x6000..=0x7FFF => {
-                eprint!("also havent implemented RTC yet");
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.write_latch(data);
+                }
             }
             _ => unreachable!()
         }
     }
     fn write_ram(&mut self, address: usize, data: u8) {
         if !self.ram_enabled {
-            eprintln!("write to ram is ignored");
             return;
         }
-        let actual_address = 0x2000 * self.ram_index + (address - 0xA000);
-        self.ram[actual_address] = data;
+        match (self.ram_index, &mut self.rtc) {
+            (0x08..=0x0C, Some(rtc)) => rtc.write(self.ram_index, data),
+            (0x00..=0x07, _) if !self.ram.is_empty() => {
+                let ram_address = self.ram_address(address);
+                self.ram[ram_address] = data;
+            }
+            _ => {}
+        }
+    }
+    fn tick(&mut self, cycles: u8) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.tick(cycles);
+        }
+    }
+    fn ram_bank(&self) -> usize {
+        self.ram_index
+    }
+    fn save_ram(&self) -> Option<Vec<u8>> {
+        if !self.battery {
+            return None;
+        }
+        match &self.rtc {
+            Some(rtc) => Some(save_with_clock(&self.ram, &rtc.save())),
+            None => Some(self.ram.clone()),
+        }
+    }
+    fn load_ram(&mut self, data: &[u8]) {
+        load_into(&mut self.ram, data);
+        if let Some(rtc) = &mut self.rtc {
+            if let Some((clock, elapsed)) = load_clock(data, self.ram.len(), CLOCK_SIZE) {
+                rtc.load(clock, elapsed);
+            }
+        }
+    }
+    fn save_has_timestamp(&self) -> bool {
+        self.rtc.is_some()
+    }
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+}
+pub struct MBC5 {
+    rom: Vec<u8>,
+    // 9 bits, and unlike the others bank 0 can be mapped here too
+    rom_index: usize,
+    total_rom_banks: usize,
+
+    ram: Vec<u8>,
+    ram_index: usize,
+    ram_enabled: bool,
+
+    // the rumble carts use bit 3 of the ram bank for the motor
+    has_rumble: bool,
+    rumbling: bool,
+    event: Option<CartridgeEvent>,
+    battery: bool,
+}
+impl MBC for MBC5 {
+    fn read_rom(&self, address: usize) -> u8 {
+        read_banked_rom(&self.rom, self.rom_index % self.total_rom_banks, address)
+    }
+    fn write_rom(&mut self, address: usize, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = data & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_index = (self.rom_index & 0x100) | data as usize,
+            0x3000..=0x3FFF => self.rom_index = (self.rom_index & 0xFF) | ((data as usize & 1) << 8),
+            0x4000..=0x5FFF => {
+                if !self.has_rumble {
+                    self.ram_index = data as usize & 0x0F;
+                    return;
+                }
+                self.ram_index = data as usize & 0x07;
+                let rumbling = data & 0x08 != 0;
+                if rumbling != self.rumbling {
+                    self.rumbling = rumbling;
+                    self.event = Some(CartridgeEvent::Rumble(rumbling));
+                }
+            }
+            _ => {}
+        }
+    }
+    fn read_ram(&self, address: usize) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[banked_ram_address(&self.ram, self.ram_index, address)]
+    }
+    fn write_ram(&mut self, address: usize, data: u8) {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return;
+        }
+        let ram_address = banked_ram_address(&self.ram, self.ram_index, address);
+        self.ram[ram_address] = data;
+    }
+    fn poll_event(&mut self) -> Option<CartridgeEvent> {
+        self.event.take()
+    }
+    fn ram_bank(&self) -> usize {
+        self.ram_index
+    }
+    fn save_ram(&self) -> Option<Vec<u8>> {
+        self.battery.then(|| self.ram.clone())
+    }
+    fn load_ram(&mut self, data: &[u8]) {
+        load_into(&mut self.ram, data);
+    }
+    fn ram(&self) -> &[u8] {
+        &self.ram
     }
 }
 
-pub fn create_mbc(rom: &Vec<u8>) -> Box<dyn MBC> {
-    let mbc_type_code = rom[0x147];
-    let rom_size_code = rom[0x148];
+/// the accelerometer reads this when the cartridge is flat
+const MBC7_CENTRE: u16 = 0x81D0;
+/// roughly how far the accelerometer moves for 1g
+const MBC7_GRAVITY: f32 = 0x70 as f32;
+
+pub struct MBC7 {
+    rom: Vec<u8>,
+    rom_index: usize,
+    total_rom_banks: usize,
+
+    // both of these have to be set for the registers to be used
+    ram_enabled: bool,
+    ram_enabled2: bool,
 
-    let rom_size;
-    if rom_size_code <= 0x08 {
-        rom_size = 2_u32.pow(rom_size_code as u32 + 1);
-    } else {
-        rom_size = match rom_size_code {
-            0x52 => 72,
-            0x53 => 80,
-            0x54 => 96,
-            _ => panic!("unsupported rom size provided"),
+    tilt: (f32, f32),
+    // the accelerometer only latches after it was erased
+    erased: bool,
+    accel_x: u16,
+    accel_y: u16,
+    eeprom: Eeprom,
+}
+impl MBC7 {
+    fn latch_accelerometer(&mut self) {
+        let (x, y) = self.tilt;
+        // tilting to the right makes the x value go down
+        self.accel_x = (MBC7_CENTRE as f32 - x * MBC7_GRAVITY) as u16;
+        self.accel_y = (MBC7_CENTRE as f32 + y * MBC7_GRAVITY) as u16;
+    }
+}
+impl MBC for MBC7 {
+    fn read_rom(&self, address: usize) -> u8 {
+        read_banked_rom(&self.rom, self.rom_index % self.total_rom_banks, address)
+    }
+    fn write_rom(&mut self, address: usize, data: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = data & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_index = data as usize,
+            0x4000..=0x5FFF => self.ram_enabled2 = data == 0x40,
+            _ => {}
+        }
+    }
+    /// there is no actual ram, just registers picked by bits 4-7 of the address
+    fn read_ram(&self, address: usize) -> u8 {
+        if !self.ram_enabled || !self.ram_enabled2 || address >= 0xB000 {
+            return 0xFF;
+        }
+        match (address >> 4) & 0x0F {
+            0x2 => self.accel_x as u8,
+            0x3 => (self.accel_x >> 8) as u8,
+            0x4 => self.accel_y as u8,
+            0x5 => (self.accel_y >> 8) as u8,
+            0x6 => 0x00,
+            0x8 => self.eeprom.read(),
+            _ => 0xFF,
+        }
+    }
+    fn write_ram(&mut self, address: usize, data: u8) {
+        if !self.ram_enabled || !self.ram_enabled2 || address >= 0xB000 {
+            return;
+        }
+        match (address >> 4) & 0x0F {
+            0x0 if data == 0x55 => {
+                self.erased = true;
+                self.accel_x = 0x8000;
+                self.accel_y = 0x8000;
+            }
+            0x1 if data == 0xAA && self.erased => {
+                self.erased = false;
+                self.latch_accelerometer();
+            }
+            0x8 => self.eeprom.write(data),
+            _ => {}
+        }
+    }
+    fn save_ram(&self) -> Option<Vec<u8>> {
+        Some(self.eeprom.save())
+    }
+    fn load_ram(&mut self, data: &[u8]) {
+        self.eeprom.load(data);
+    }
+    fn set_tilt(&mut self, x: f32, y: f32) {
+        self.tilt = (x.clamp(-1.0, 1.0), y.clamp(-1.0, 1.0));
+    }
+}
+
+/// a multicart mapper which starts up in the menu in the last 32KiB, then
+/// gets mapped onto the picked game and locks itself so the game cant get out.
+/// the registers are from https://gbdev.io/pandocs/MMM01.html
+pub struct MMM01 {
+    rom: Vec<u8>,
+    total_rom_banks: usize,
+    // 9 bits, the upper ones are only writable before mapping
+    rom_bank: usize,
+    // the bits of the low 5 which the game cant change once mapped
+    rom_mask: usize,
+
+    ram: Vec<u8>,
+    ram_bank: usize,
+    // the bits of the ram bank which the game cant change once mapped
+    ram_mask: usize,
+    ram_enabled: bool,
+
+    mode: bool,
+    mode_locked: bool,
+    mapped: bool,
+    battery: bool,
+}
+impl MMM01 {
+    fn new(mut rom: Vec<u8>, ram: Vec<u8>, battery: bool) -> Self {
+        // some dumps have the menu first, but it lives at the end on the real cart
+        if !has_menu_at_end(&rom) && is_mmm01_header(&rom, 0) {
+            rom.rotate_left(0x8000);
+        }
+        Self {
+            total_rom_banks: rom.len() / 0x4000,
+            rom,
+            rom_bank: 0,
+            rom_mask: 0,
+            ram,
+            ram_bank: 0,
+            ram_mask: 0,
+            ram_enabled: false,
+            mode: false,
+            mode_locked: false,
+            mapped: false,
+            battery,
+        }
+    }
+    fn writable_rom_bits(&self) -> usize {
+        if self.mapped { 0x1F & !self.rom_mask } else { 0x7F }
+    }
+    fn ram_address(&self, address: usize) -> usize {
+        let bank = if self.mode { self.ram_bank } else { self.ram_bank & !0x03 };
+        (0x2000 * bank + (address - 0xA000)) % self.ram.len()
+    }
+}
+impl MBC for MMM01 {
+    fn read_rom(&self, address: usize) -> u8 {
+        let bank = match (self.mapped, address) {
+            // the menu is the last 32KiB until the game gets mapped
+            (false, 0x0000..=0x3FFF) => self.total_rom_banks - 2,
+            (false, _) => self.total_rom_banks - 1,
+            // the game's bank 0 is wherever the bits it cant change point
+            (true, 0x0000..=0x3FFF) if !self.mode => self.rom_bank & !self.writable_rom_bits(),
+            (true, 0x0000..=0x3FFF) => self.rom_bank & !0x1F,
+            (true, _) => {
+                let writable = self.writable_rom_bits();
+                // like the mbc1 a 0 in the game's part of the bank becomes a 1
+                match self.rom_bank & writable {
+                    0 => self.rom_bank | 1,
+                    _ => self.rom_bank,
+                }
+            }
+        };
+        self.rom[0x4000 * (bank % self.total_rom_banks) + (address % 0x4000)]
+    }
+    fn write_rom(&mut self, address: usize, data: u8) {
+        let data = data as usize;
+        match address {
+            0x0000..=0x1FFF => {
+                self.ram_enabled = data & 0x0F == 0x0A;
+                if !self.mapped {
+                    self.ram_mask = (data >> 4) & 0x03;
+                    self.mapped = data & 0x40 != 0;
+                }
+            }
+            0x2000..=0x3FFF => {
+                let writable = self.writable_rom_bits();
+                self.rom_bank = (self.rom_bank & !writable) | (data & writable);
+            }
+            0x4000..=0x5FFF => {
+                let writable = if self.mapped { 0x03 & !self.ram_mask } else { 0x0F };
+                self.ram_bank = (self.ram_bank & !writable) | (data & writable);
+                if !self.mapped {
+                    self.rom_bank = (self.rom_bank & 0x7F) | (data & 0x30) << 3;
+                    self.mode_locked = data & 0x40 != 0;
+                }
+            }
+            0x6000..=0x7FFF => {
+                if !(self.mapped && self.mode_locked) {
+                    self.mode = data & 1 == 1;
+                }
+                if !self.mapped {
+                    self.rom_mask = (data & 0x3C) >> 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    fn read_ram(&self, address: usize) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[self.ram_address(address)]
+    }
+    fn write_ram(&mut self, address: usize, data: u8) {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return;
+        }
+        let ram_address = self.ram_address(address);
+        self.ram[ram_address] = data;
+    }
+    fn save_ram(&self) -> Option<Vec<u8>> {
+        self.battery.then(|| self.ram.clone())
+    }
+    fn load_ram(&mut self, data: &[u8]) {
+        load_into(&mut self.ram, data);
+    }
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+}
+
+/// whether the header at the offset says mmm01
+fn is_mmm01_header(rom: &[u8], offset: usize) -> bool {
+    matches!(rom.get(offset + 0x147), Some(0x0B..=0x0D))
+}
+
+/// whether the last 32KiB hold a real mmm01 menu. The end of the rom is just
+/// game data for every other cartridge, so it has to have a valid header too
+fn has_menu_at_end(rom: &[u8]) -> bool {
+    let menu = rom.len().saturating_sub(0x8000);
+    is_mmm01_header(rom, menu) && is_valid_header(&rom[menu..])
+}
+
+/// a header the boot rom would accept, with the logo and a checksum that adds up
+fn is_valid_header(rom: &[u8]) -> bool {
+    rom.len() >= 0x150 && Header::new(rom).has_valid_logo() && header_checksum(rom) == rom[0x14D]
+}
+
+/// reads from a rom where bank 0 is fixed and the bank at 0x4000-0x7FFF can be switched
+pub fn read_banked_rom(rom: &[u8], bank: usize, address: usize) -> u8 {
+    if address <= 0x3FFF {
+        return rom[address];
+    }
+    rom[0x4000 * bank + (address - 0x4000)]
+}
+
+/// where 0xA000-0xBFFF lands in the ram with the bank that is mapped there,
+/// ram smaller than a bank just gets mirrored
+pub fn banked_ram_address(ram: &[u8], bank: usize, address: usize) -> usize {
+    (0x2000 * bank + (address - 0xA000)) % ram.len()
+}
+
+/// the rom bank written to 0x2000-0x3FFF, where bank 0 gets mapped as 1
+/// like on the mbc1 since it is already at 0x0000-0x3FFF
+pub fn rom_bank_number(data: u8, mask: u8) -> usize {
+    match data & mask {
+        0 => 1,
+        bank => bank as usize,
+    }
+}
+
+/// copies as much of a save file as fits into the ram
+pub fn load_into(ram: &mut [u8], data: &[u8]) {
+    let length = ram.len().min(data.len());
+    ram[..length].copy_from_slice(&data[..length]);
+}
+
+/// whether the cartridge type has a battery keeping its ram alive
+fn has_battery(mbc_type_code: u8) -> bool {
+    matches!(mbc_type_code, 0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFC | 0xFD | 0xFE | 0xFF)
+}
+
+/// multicarts are 1MiB and every game in them has its own header, so
+/// the logo shows up again at the start of the banks 0x10 apart
+fn is_multicart(rom: &[u8]) -> bool {
+    if rom.len() != 0x100000 {
+        return false;
+    }
+    // bank 0 is the menu, which has a logo either way
+    (1..4).any(|game| {
+        let header = game * 0x10 * 0x4000 + 0x104;
+        rom[header..header + 0x30] == NINTENDO_LOGO
+    })
+}
+
+pub fn create_mbc(rom: &Vec<u8>, ram: &mut RamFiller) -> Result<Box<dyn MBC>, CartridgeError> {
+    // the header at the start of an mmm01 rom is for the first game, the
+    // menu's header in the last 32KiB is the one that says what the cart is
+    let menu_at_end = has_menu_at_end(rom);
+    if rom.len() >= 0x10000 && (menu_at_end || is_mmm01_header(rom, 0)) {
+        let menu_header = match menu_at_end {
+            true => Header::new(&rom[rom.len() - 0x8000..]),
+            false => Header::new(rom),
         };
+        let ram_size = menu_header.ram_size()?;
+        let battery = has_battery(menu_header.mbc_type);
+        return Ok(Box::new(MMM01::new(rom.clone(), ram.fill(ram_size), battery)));
     }
-    let rom_length = 0x3FFF + 0x4000 * (rom_size - 1) as usize;
-    let rom_bank = rom[0x0000..=rom_length].to_vec();
 
-    //let ram_size_code = rom[0x149];
-    /* let ram_size = match ram_size_code {
-        0x00 => 1,
-        _ => panic!("unsupported ram_size provided"),
-    }; */
+    let header = Header::new(rom);
+    let mbc_type_code = header.mbc_type;
 
-    match mbc_type_code {
-        0x00 | 0x01 | 0x02 | 0x03 => {
-            let ram_bank = vec![0; 0x2000];
+    let rom_size = header.rom_banks()?;
+    let rom_length = 0x4000 * rom_size;
+    if rom.len() < rom_length {
+        return Err(CartridgeError::Truncated { file: rom.len(), header: rom_length });
+    }
+    let rom_bank = rom[0x0000..rom_length].to_vec();
 
+    let ram_size = header.ram_size()?;
+
+    let mbc: Box<dyn MBC> = match mbc_type_code {
+        0x00 | 0x01 | 0x02 | 0x03 => {
             Box::new(MBC1 {
                 rom_banks: rom_bank,
-                total_rom_banks: rom_size as usize,
-                high_bank_index: 1,
-                zero_bank_index: 0,
+                total_rom_banks: rom_size,
+                low_bank: 1,
+                high_bank: 0,
 
-                ram: ram_bank,
-                ram_index: 0,
-
-                ram_enabled: true,
+                ram: ram.fill(ram_size),
+                ram_enabled: false,
                 mode: false,
+                battery: has_battery(mbc_type_code),
+                multicart: is_multicart(rom),
             })
         }
         0x05 | 0x06 => {
-            let ram = vec![0; 256];
+            // only the lower 4 bits of each byte exist
+            let ram = ram.fill(0x200).iter().map(|b| b & 0x0F).collect();
             Box::new(MBC2 {
                 rom_banks: rom_bank,
                 high_rom_index: 1,
-                ram_enabled: true,
+                total_rom_banks: rom_size,
+                ram_enabled: false,
                 ram,
+                battery: has_battery(mbc_type_code),
             })
         }
         0x0F..=0x13 => {
-           let ram = rom[rom_length+1..].to_vec();
            Box::new(MBC3 {
             rom: rom_bank,
             high_rom_index: 1,
-            ram,
+            total_rom_banks: rom_size,
+            ram: ram.fill(ram_size),
             ram_index: 0,
-            ram_enabled: true,
+            ram_enabled: false,
+            // only 0x0F and 0x10 have the timer
+            rtc: (mbc_type_code <= 0x10).then(Rtc::new),
+            battery: has_battery(mbc_type_code),
            })
         }
-        _ => panic!("unsupported MBC type"),
+        0x19..=0x1E => {
+            Box::new(MBC5 {
+                rom: rom_bank,
+                rom_index: 1,
+                total_rom_banks: rom_size,
+                ram: ram.fill(ram_size),
+                ram_index: 0,
+                ram_enabled: false,
+                has_rumble: mbc_type_code >= 0x1C,
+                rumbling: false,
+                event: None,
+                battery: has_battery(mbc_type_code),
+            })
+        }
+        0x22 => {
+            Box::new(MBC7 {
+                rom: rom_bank,
+                rom_index: 1,
+                total_rom_banks: rom_size,
+                ram_enabled: false,
+                ram_enabled2: false,
+                tilt: (0.0, 0.0),
+                erased: false,
+                accel_x: 0x8000,
+                accel_y: 0x8000,
+                eeprom: Eeprom::new(),
+            })
+        }
+        // the camera always has 128KiB, whatever the header says
+        0xFC => Box::new(PocketCamera::new(rom_bank, rom_size, ram.fill(ram_size.max(0x20000)))),
+        0xFE => Box::new(HuC3::new(rom_bank, rom_size, ram.fill(ram_size))),
+        0xFF => Box::new(HuC1::new(rom_bank, rom_size, ram.fill(ram_size))),
+        code => return Err(CartridgeError::UnsupportedMbc(code)),
+    };
+    Ok(mbc)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::RamInit;
+
+    /// a rom with each bank's first byte set to its number
+    fn numbered_rom(banks: usize) -> Vec<u8> {
+        let mut rom = vec![0; banks * 0x4000];
+        for bank in 0..banks {
+            rom[bank * 0x4000] = bank as u8;
+        }
+        rom
+    }
+
+    /// puts a header the boot rom would accept at the offset
+    fn write_header(rom: &mut [u8], offset: usize, mbc_type: u8) {
+        let rom_size_code = (rom.len() / 0x8000).trailing_zeros() as u8;
+        let header = &mut rom[offset..];
+        header[0x104..0x134].copy_from_slice(&NINTENDO_LOGO);
+        header[0x147] = mbc_type;
+        header[0x148] = rom_size_code;
+        header[0x14D] = header_checksum(header);
+    }
+
+    fn create(rom: &Vec<u8>) -> Box<dyn MBC> {
+        create_mbc(rom, &mut RamFiller::new(RamInit::Zero, Some(0))).unwrap()
+    }
+
+    #[test]
+    fn mmm01_menu_at_the_end() {
+        // the first game's header says mmm01 too, but the menu is already in place
+        let mut rom = numbered_rom(8);
+        write_header(&mut rom, 0, 0x0B);
+        write_header(&mut rom, 6 * 0x4000, 0x0B);
+        let mbc = create(&rom);
+        assert_eq!(mbc.read_rom(0x0000), 6);
+        assert_eq!(mbc.read_rom(0x4000), 7);
+    }
+
+    #[test]
+    fn mmm01_menu_at_the_start() {
+        let mut rom = numbered_rom(8);
+        write_header(&mut rom, 0, 0x0B);
+        write_header(&mut rom, 6 * 0x4000, 0x01);
+        let mbc = create(&rom);
+        assert_eq!(mbc.read_rom(0x0000), 0);
+        assert_eq!(mbc.read_rom(0x4000), 1);
+    }
+
+    #[test]
+    fn stray_mmm01_byte_at_the_end() {
+        // the type byte lines up with game data, but there is no header around it
+        let mut rom = numbered_rom(8);
+        write_header(&mut rom, 0, 0x01);
+        rom[6 * 0x4000 + 0x147] = 0x0B;
+        let mut mbc = create(&rom);
+        assert_eq!(mbc.read_rom(0x0000), 0);
+        mbc.write_rom(0x2000, 3);
+        assert_eq!(mbc.read_rom(0x4000), 3);
+    }
+
+    #[test]
+    fn mmm01_maps_a_game_and_locks() {
+        let mut rom = numbered_rom(8);
+        write_header(&mut rom, 6 * 0x4000, 0x0B);
+        let mut mbc = create(&rom);
+
+        // the menu picks a 32KiB game at bank 2, then maps it
+        mbc.write_rom(0x2000, 0x02);
+        mbc.write_rom(0x6000, 0x3C);
+        mbc.write_rom(0x0000, 0x40);
+        assert_eq!(mbc.read_rom(0x0000), 2);
+        assert_eq!(mbc.read_rom(0x4000), 3);
+
+        // the game can only reach its own banks, and cant go back to the menu
+        mbc.write_rom(0x2000, 0x05);
+        assert_eq!(mbc.read_rom(0x4000), 3);
+        mbc.write_rom(0x0000, 0x00);
+        mbc.write_rom(0x2000, 0x06);
+        assert_eq!(mbc.read_rom(0x0000), 2);
+    }
+
+    #[test]
+    fn mbc1m_detected_by_the_second_logo() {
+        let mut rom = numbered_rom(64);
+        write_header(&mut rom, 0, 0x01);
+        rom[0x10 * 0x4000 + 0x104..0x10 * 0x4000 + 0x134].copy_from_slice(&NINTENDO_LOGO);
+        let mut mbc = create(&rom);
+
+        // bit 4 of the low register is ignored, the high one starts at bit 4
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_rom(0x2000, 0x13);
+        assert_eq!(mbc.read_rom(0x4000), 0x13);
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(0x0000), 0x10);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn plain_mbc1_uses_5_bits() {
+        let mut rom = numbered_rom(64);
+        write_header(&mut rom, 0, 0x01);
+        let mut mbc = create(&rom);
+
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_rom(0x2000, 0x13);
+        assert_eq!(mbc.read_rom(0x4000), 0x33);
+    }
+}
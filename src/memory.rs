@@ -1,85 +1,383 @@
 use crate::joypad;
-use crate::mbc::{create_mbc, MBC};
-use crate::util::{little_endian_combine, JOYPAD_ADDRESS};
-use crate::util::NINTENDO_LOGO;
+use crate::args::RamInit;
+use crate::model::Model;
+use std::cell::Cell;
+use crate::gpu::{PpuState, PpuWrite};
+use crate::io::IoRegisters;
+use crate::mbc::{create_mbc, CartridgeEvent, MBC};
+use crate::cartridge::{CartridgeError, Header};
+use crate::util::{little_endian_combine, JOYPAD_ADDRESS, BOOT_ROM_ADDRESS};
 use crate::util::TimerRegisters;
 use crate::util::INTERRUPT_F_ADDRESS;
+use crate::util::ppu::PpuRegisters;
+use crate::cheats::{Cheat, CheatKind};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+/// fills all of the different rams with their power on values.
+/// with the same seed the random values are always the same
+pub struct RamFiller {
+    init: RamInit,
+    rng: StdRng,
+}
+impl RamFiller {
+    pub fn new(init: RamInit, seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(|| {
+            let seed = rand::random();
+            if let RamInit::Random = init {
+                // so the run can be reproduced later on
+                eprintln!("ram was randomised with seed {seed}");
+            }
+            seed
+        });
+        Self { init, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    pub fn fill(&mut self, size: usize) -> Vec<u8> {
+        match self.init {
+            RamInit::Zero => vec![0; size],
+            RamInit::Random => (0..size).map(|_| self.rng.gen()).collect(),
+            // blocks of 0x00 and 0xFF, roughly what a lot of dmgs power on with
+            RamInit::Pattern => (0..size).map(|i| if (i / 8) % 2 == 0 { 0x00 } else { 0xFF }).collect(),
+        }
+    }
+}
 
 pub struct Memory {
-    pub mem: Vec<u8>,
+    // 0xC000-0xDFFF, also echoed at 0xE000-0xFDFF
+    wram: Vec<u8>,
+    // 0x8000-0x9FFF
+    vram: Vec<u8>,
+    // 0xFE00-0xFE9F
+    oam: Vec<u8>,
+    // 0xFF80-0xFFFE
+    hram: Vec<u8>,
+    // 0xFF00-0xFF7F
+    io: IoRegisters,
+    // 0xFFFF
+    ie: u8,
+
     mbc: Box<dyn MBC>,
+    model: Model,
+    // mapped over the start of the rom until 0xFF50 gets written to
+    boot_rom: Option<Vec<u8>>,
     div: u16,
+    dma: Dma,
+    ppu_state: PpuState,
+    // the row of oam the ppu is currently looking at during its oam scan
+    oam_scan_row: usize,
+    cheats: Vec<Cheat>,
+    // how many dots into the cpu step the accesses have got to, each read or write is an M-cycle.
+    // only the cpu's accesses count, so it is stopped outside of begin_step and end_step
+    access_cycles: Cell<u8>,
+    clock_running: bool,
+    // the ppu's registers written to this step, the ppu catches up to each one
+    ppu_writes: Vec<PpuWrite>,
+}
+
+/// the different kinds of cpu accesses which corrupt oam
+pub enum OamBug {
+    Write,
+    ReadIncrease,
 }
 impl Memory {
-    pub fn new(rom: Vec<u8>, booted: bool) -> Self {
-        let mut memory = Memory::new_unbooted(rom);
-        if !booted {
-            // just so it passes the boot rom's check
-            for (i, val) in NINTENDO_LOGO.iter().enumerate() {
-                memory.mem[0x104 + i] = *val;
+    pub fn new(rom: Vec<u8>, boot_rom: Option<Vec<u8>>, model: Model, ram: &mut RamFiller) -> Result<Self, CartridgeError> {
+        if boot_rom.is_some() && !Header::new(&rom).has_valid_logo() {
+            // the real boot rom just locks up, which is what is going to happen here too
+            eprintln!("the cartridge's logo is invalid, the boot rom will not start it");
+        }
+
+        let mut memory = Memory::new_unbooted(rom, model, ram)?;
+        if boot_rom.is_some() {
+            memory.boot_rom = boot_rom;
+            return Ok(memory)
+        }
+
+        for (address, data) in model.io_registers() {
+            memory.io.set(address, data);
+        }
+        memory.div = model.div();
+        Ok(memory)
+    }
+
+    fn new_unbooted(rom: Vec<u8>, model: Model, ram: &mut RamFiller) -> Result<Self, CartridgeError> {
+        let mbc = create_mbc(&rom, ram)?;
+        Ok(Self {
+            wram: ram.fill(0x2000),
+            vram: ram.fill(0x2000),
+            oam: vec![0; 0xA0],
+            hram: ram.fill(0x7F),
+            io: IoRegisters::new(),
+            ie: 0,
+            div: 0,
+            model,
+            mbc,
+            boot_rom: None,
+            dma: Dma::default(),
+            // the lcd is off at power on, so nothing is blocked until the ppu starts
+            ppu_state: PpuState::HBlank,
+            oam_scan_row: 0,
+            cheats: Vec::new(),
+            access_cycles: Cell::new(0),
+            clock_running: false,
+            ppu_writes: Vec::new(),
+        })
+    }
+
+    pub fn save_ram(&self) -> Option<Vec<u8>> {
+        self.mbc.save_ram()
+    }
+    pub fn load_ram(&mut self, data: &[u8]) {
+        self.mbc.load_ram(data);
+    }
+    pub fn save_has_timestamp(&self) -> bool {
+        self.mbc.save_has_timestamp()
+    }
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.mbc.set_tilt(x, y);
+    }
+    pub fn set_camera_frame(&mut self, frame: &[u8]) {
+        self.mbc.set_camera_frame(frame);
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+    pub fn add_cheat(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat);
+    }
+    pub fn toggle_cheat(&mut self, index: usize) -> Option<&Cheat> {
+        let cheat = self.cheats.get_mut(index)?;
+        cheat.enabled = !cheat.enabled;
+        Some(cheat)
+    }
+
+    /// every bank of the cartridge's ram, even while the game has it disabled
+    pub fn cartridge_ram(&self) -> &[u8] {
+        self.mbc.ram()
+    }
+
+    /// whether the bank is the one currently at the address, for
+    /// anything that cares which ram bank a value is in
+    pub fn bank_mapped(&self, bank: u8, address: u16) -> bool {
+        match address {
+            0xA000..=0xBFFF => self.mbc.ram_bank() == bank as usize,
+            // without the colour banking only bank 1 can be at 0xD000
+            0xD000..=0xDFFF => bank <= 1,
+            _ => true,
+        }
+    }
+
+    /// writes all of the gameshark codes, this happens once a frame.
+    /// the ones for a specific bank only get written while it is mapped in
+    pub fn apply_cheats(&mut self) {
+        let codes: Vec<CheatKind> = self.cheats.iter()
+            .filter(|cheat| cheat.enabled)
+            .map(|cheat| cheat.kind)
+            .collect();
+        for code in codes {
+            let CheatKind::GameShark { bank, address, data } = code else {
+                continue;
+            };
+            if bank.is_none_or(|bank| self.bank_mapped(bank, address)) {
+                self.write(address, data);
+            }
+        }
+    }
+
+    pub fn poll_cartridge_event(&mut self) -> Option<CartridgeEvent> {
+        self.mbc.poll_event()
+    }
+
+    /// the cpu is starting another step, so its accesses get timed from zero again
+    pub fn begin_step(&mut self) {
+        self.access_cycles.set(0);
+        self.clock_running = true;
+    }
+    /// anything reading or writing after this (the ppu, cheats, the
+    /// console) isnt the cpu so it doesnt move the clock along, and its
+    /// writes to the ppu's registers land at the start of the next update
+    pub fn end_step(&mut self) {
+        self.clock_running = false;
+        self.access_cycles.set(0);
+    }
+    fn count_access(&self) {
+        if self.clock_running {
+            self.access_cycles.set(self.access_cycles.get().saturating_add(4));
+        }
+    }
+
+    /// clearing the flag of an interrupt being handled happens inside
+    /// the cpu, so it isnt a bus access
+    pub fn acknowledge_interrupt(&mut self, interrupt_flag: u8) {
+        self.io.set(INTERRUPT_F_ADDRESS, interrupt_flag);
+    }
+
+    /// the writes to the ppu's registers this step, with the registers put back
+    /// to how they were before so the ppu can replay them at the right dot
+    pub fn take_ppu_writes(&mut self) -> Vec<PpuWrite> {
+        let writes = std::mem::take(&mut self.ppu_writes);
+        for write in writes.iter().rev() {
+            self.io.set(write.address, write.old);
+        }
+        writes
+    }
+    pub fn replay_ppu_write(&mut self, write: &PpuWrite) {
+        if write.address == PpuRegisters::LCDC as u16 && write.data & 0b1000_0000 == 0 {
+            self.lcd_off();
+        }
+        self.io.set(write.address, write.data);
+    }
+
+    pub fn set_ppu_state(&mut self, state: PpuState) {
+        self.ppu_state = state;
+    }
+
+    pub fn set_oam_scan_row(&mut self, row: usize) {
+        self.oam_scan_row = row;
+    }
+
+    /// when a 16-bit register pointing into 0xFE00-0xFEFF changes during
+    /// the oam scan, the dmg corrupts the row the ppu is currently reading.
+    /// all the patterns are from https://gbdev.io/pandocs/OAM_Corruption_Bug.html
+    pub fn oam_bug(&mut self, address: u16, kind: OamBug) {
+        if self.model.is_cgb() {
+            return;
+        }
+        if !(0xFE00..=0xFEFF).contains(&address) || self.ppu_state != PpuState::Oam {
+            return;
+        }
+        // the first row never gets corrupted
+        let row = self.oam_scan_row;
+        if row == 0 || row >= 20 {
+            return;
+        }
+
+        match kind {
+            OamBug::Write => {
+                let a = self.oam_word(row, 0);
+                let b = self.oam_word(row - 1, 0);
+                let c = self.oam_word(row - 1, 2);
+                self.set_oam_word(row, 0, ((a ^ c) & (b ^ c)) ^ c);
+                self.copy_oam_row(row - 1, row, 1);
+            }
+            OamBug::ReadIncrease => {
+                // the first four rows and the last row only get the normal read corruption
+                if (4..19).contains(&row) {
+                    let a = self.oam_word(row - 2, 0);
+                    let b = self.oam_word(row - 1, 0);
+                    let c = self.oam_word(row, 0);
+                    let d = self.oam_word(row - 1, 2);
+                    self.set_oam_word(row - 1, 0, (b & (a | c | d)) | (a & c & d));
+                    self.copy_oam_row(row - 1, row - 2, 0);
+                    self.copy_oam_row(row - 1, row, 0);
+                }
+                self.oam_read_corruption(row);
             }
-            return memory
         }
+    }
 
-        memory.mem[0xFF00] = 0xFF;
-        memory.mem[0xFF02] = 0x7E;
-        memory.div = 0x1800;
-        memory.mem[0xFF07] = 0xF8;
-        memory.mem[0xFF0F] = 0xE1;
-        memory.mem[0xFF40] = 0x91;
-        memory.mem[0xFF41] = 0x81;
-        memory.mem[0xFF44] = 0x00;
-        memory.mem[0xFF46] = 0xFF;
+    fn oam_read_corruption(&mut self, row: usize) {
+        let a = self.oam_word(row, 0);
+        let b = self.oam_word(row - 1, 0);
+        let c = self.oam_word(row - 1, 2);
+        self.set_oam_word(row, 0, b | (a & c));
+        self.copy_oam_row(row - 1, row, 1);
+    }
 
-        memory
+    /// each row of oam is 8 bytes, which is 4 words
+    fn oam_word(&self, row: usize, word: usize) -> u16 {
+        let index = row * 8 + word * 2;
+        little_endian_combine(self.oam[index], self.oam[index + 1])
+    }
+    fn set_oam_word(&mut self, row: usize, word: usize, data: u16) {
+        let index = row * 8 + word * 2;
+        self.oam[index] = (data & 0xFF) as u8;
+        self.oam[index + 1] = (data >> 8) as u8;
+    }
+    /// copies the words of one row to another, starting at `first_word`
+    fn copy_oam_row(&mut self, from: usize, to: usize, first_word: usize) {
+        let start = first_word * 2;
+        self.oam.copy_within(from * 8 + start..from * 8 + 8, to * 8 + start);
     }
 
-    fn new_unbooted(rom: Vec<u8>) -> Self {
-        let memory = vec![0; 0x10000];
-        let mbc = create_mbc(&rom);
-        Self { mem: memory, div: 0x1800, mbc }
+    /// oam is in use by the ppu during the oam scan and drawing
+    fn oam_blocked(&self) -> bool {
+        matches!(self.ppu_state, PpuState::Oam | PpuState::Drawing)
+    }
+    /// vram is only in use while drawing
+    fn vram_blocked(&self) -> bool {
+        self.ppu_state == PpuState::Drawing
     }
 
     /// this completes a write to memory and follows the rules of writing
-    /// to memory. Currently doesnt handle memory bank controllers but I will
-    /// eventually implement them
+    /// to memory, the rom and external ram get passed on to the mbc
     pub fn write(&mut self, address: u16, data: u8) {
-        let address = address as usize;
+        self.count_access();
+        // the cpu cant reach anything outside of hram while a dma is running
+        if self.dma.blocks(address as usize) {
+            return;
+        }
 
-        if is_within_rom(address) {
-            self.mbc.write_rom(address, data);
+        let index = address as usize;
+        // the ppu just ignores writes to whatever it is currently using
+        if is_within_vram(index) && self.vram_blocked() {
             return;
         }
-        if is_within_ram(address) {
-            self.mbc.write_ram(address, data);
+        if is_within_oam(index) && self.oam_blocked() {
             return;
         }
-        if address == 0xFF40 && data&0b1000_0000 == 0 {
-            self.mem[0xFF41] &= 0b1111_1100;
+        match index {
+            0x0000..=0x7FFF => self.mbc.write_rom(index, data),
+            0x8000..=0x9FFF => self.vram[index - 0x8000] = data,
+            0xA000..=0xBFFF => self.mbc.write_ram(index, data),
+            0xC000..=0xFDFF => self.wram[index & 0x1FFF] = data,
+            0xFE00..=0xFE9F => self.oam[index - 0xFE00] = data,
+            // the unusable region, writes here go nowhere
+            0xFEA0..=0xFEFF => {},
+            0xFF00..=0xFF7F => self.write_io(address, data),
+            0xFF80..=0xFFFE => self.hram[index - 0xFF80] = data,
+            0xFFFF => self.ie = data,
+            _ => unreachable!(),
+        }
+    }
+
+    fn write_io(&mut self, address: u16, data: u8) {
+        // turning the lcd off happens when the ppu replays the write
+        if is_ppu_register(address) {
+            let old = self.io.get(address);
+            self.ppu_writes.push(PpuWrite { cycle: self.access_cycles.get(), address, old, data });
         }
 
         // only the upper bits of joypad register are writable
         if address == JOYPAD_ADDRESS {
-            self.mem[JOYPAD_ADDRESS] &=  0x0F;
-            self.mem[JOYPAD_ADDRESS] |= data & 0xF0;
+            let joypad = self.io.get(JOYPAD_ADDRESS);
+            self.io.set(JOYPAD_ADDRESS, (joypad & 0x0F) | (data & 0xF0));
             return;
         }
         if address == 0xFF46 {
-            process_dma(self, data);
+            self.io.set(address, data);
+            self.dma.request(data);
             return;
         }
+        // any non zero write unmaps the boot rom for good
+        if address == BOOT_ROM_ADDRESS && data != 0 {
+            self.boot_rom = None;
+        }
         // the internal DIV 
-        if address == TimerRegisters::DIV as usize {
+        if address == TimerRegisters::DIV as u16 {
             self.div = 0;
             return;
         }
+        self.io.set(address, data);
+    }
 
-        self.mem[address] = data;
-        if address >= 0xC000 && address <= 0xDE00 {
-            self.mem[address+0x2000] = data;
-        } else if address >= 0xE000 && address <= 0xFE00 {
-            self.mem[address-0x2000] = data;
-        }
+    fn lcd_off(&mut self) {
+        let stat = self.io.get(0xFF41);
+        self.io.set(0xFF41, stat & 0b1111_1100);
+        // with the lcd off everything becomes accessible
+        self.ppu_state = PpuState::HBlank;
     }
 
     /// this follows the little endian encoding which th gameboy follows. 
@@ -90,35 +388,88 @@ impl Memory {
         self.write(address+1, (data >> 8) as u8);
     }
 
+    /// reads any address without caring about what the ppu or dma is doing
     pub fn unchecked_read(&self, address: u16) -> u8 {
-        self.mem[address as usize]
+        let index = address as usize;
+        match index {
+            0x0000..=0x08FF if self.boot_rom_mapped(index) => self.boot_rom.as_ref().unwrap()[index],
+            0x0000..=0x7FFF => self.read_rom(index),
+            0x8000..=0x9FFF => self.vram[index - 0x8000],
+            0xA000..=0xBFFF => self.mbc.read_ram(index),
+            0xC000..=0xFDFF => self.wram[index & 0x1FFF],
+            0xFE00..=0xFE9F => self.oam[index - 0xFE00],
+            0xFEA0..=0xFEFF => self.read_unusable(index),
+            0xFF00..=0xFF7F => self.read_io(address),
+            0xFF80..=0xFFFE => self.hram[index - 0xFF80],
+            0xFFFF => self.ie,
+            _ => unreachable!(),
+        }
     }
 
-    /// reads from memory
-    pub fn read(&self, address: u16) -> u8 {
-        let address = address as usize;
+    /// the colour boot roms are 0x900 bytes long, the
+    /// cartridge header shows through the gap at 0x100-0x1FF
+    fn boot_rom_mapped(&self, address: usize) -> bool {
+        match &self.boot_rom {
+            Some(boot_rom) => address < 0x100 || (0x200..boot_rom.len()).contains(&address),
+            None => false,
+        }
+    }
 
-        if is_within_rom(address) {
-            return self.mbc.read_rom(address);
+    /// game genie codes swap out what the rom gives back, the ones
+    /// with a compare byte only do it when the rom has that byte
+    fn read_rom(&self, address: usize) -> u8 {
+        let data = self.mbc.read_rom(address);
+        for cheat in self.cheats.iter().filter(|cheat| cheat.enabled) {
+            if let CheatKind::GameGenie { address: cheat_address, data: cheat_data, compare } = cheat.kind {
+                if cheat_address as usize == address && compare.is_none_or(|compare| compare == data) {
+                    return cheat_data;
+                }
+            }
         }
-        if is_within_ram(address) {
-            return self.mbc.read_ram(address);
+        data
+    }
+
+    /// the dmg just returns 0 for the unusable region, the later colour
+    /// models repeat the upper nybble of the lower address byte
+    fn read_unusable(&self, address: usize) -> u8 {
+        if !self.model.is_cgb() {
+            return 0x00;
         }
+        let nybble = (address & 0xF0) as u8;
+        nybble | nybble >> 4
+    }
 
-        if address == JOYPAD_ADDRESS as usize {
-            return joypad(self.mem[JOYPAD_ADDRESS]);
+    fn read_io(&self, address: u16) -> u8 {
+        if address == JOYPAD_ADDRESS {
+            return joypad(self.io.get(JOYPAD_ADDRESS)) | 0b1100_0000;
         }
-        if address == 0xFF04 {
+        if address == TimerRegisters::DIV as u16 {
             return (self.div >> 8) as u8;
         }
+        self.io.read(address)
+    }
+
+    /// reads from memory
+    pub fn read(&self, address: u16) -> u8 {
+        self.count_access();
+        let index = address as usize;
+
+        if self.dma.blocks(index) {
+            // reading from the same bus as the dma just gives whatever it is reading
+            if is_within_oam(index) || !self.dma.shares_bus(index) {
+                return 0xFF;
+            }
+            return self.dma.current;
+        }
 
-        // only the second bit of the stat register matter
-        let blocker = self.mem[0xFF41] & 0b0000_0011;
-        match (blocker, is_within_oam(address), is_within_vram(address)) {
-            (2, true, _) => 0xFF,
-            (3, true, true) => 0xFF,
-            _ => self.mem[address]
+        if is_within_vram(index) && self.vram_blocked() {
+            return 0xFF;
+        }
+        // the unusable region also gets blocked along with oam
+        if (is_within_oam(index) || is_within_unusable(index)) && self.oam_blocked() {
+            return 0xFF;
         }
+        self.unchecked_read(address)
     }
 
     /// just makes reading 16-bits of data more convenient
@@ -128,66 +479,85 @@ impl Memory {
 
     pub fn oam_search(&self, index: u8) -> [u8; 4] {
         // the start of oam plus the index spacing
-        let start = 0xFE00 + (index as usize * 4);
-        (self.mem[start], self.mem[start+1], self.mem[start+2], self.mem[start+3]).try_into().unwrap()
-    }
-
-    pub fn read_bg_tile(&self, address: u16, addressing: u16) -> [u16; 8] {
-        // the address is the tile in the background
-        // not the data for the tile
-        let tile_index = self.mem[address as usize];
-
-        let tile_address = match addressing {
-            0x8000 => addressing + (tile_index as u16) * 16,
-            0x8800 => 0x9000_u16.wrapping_add_signed((tile_index as i8 as i16) * 16),
-            _ => panic!("invalid addressing"),
-        };
-        self.read_tile(tile_address)
-    }
-
-    pub fn read_tile(&self, address: u16) -> [u16; 8] {
-        let mut tile_data: Vec<u16> = Vec::new();
-        for i in 0..8 {
-            let low = i*2;
-            let high = (i*2) + 1;
-
-            let low_data = self.mem[address as usize+low];
-            let high_data = self.mem[address as usize+high];
-            
-            let mut line_data: u16 = 0;
-            for j in 0..8 {
-                let new_pixel = (low_data>>j)&1 | ((high_data>>j)&1)<<1;
-                line_data |= (new_pixel as u16) << (j*2);
-            }
-            tile_data.push(line_data);
+        let start = index as usize * 4;
+        self.oam[start..start+4].try_into().unwrap()
+    }
+}
+
+/// an OAM DMA transfer. After being requested there is a one M-cycle
+/// delay and then one byte gets copied every M-cycle for 160 M-cycles
+#[derive(Default)]
+struct Dma {
+    source: usize,
+    // how many bytes have been copied so far
+    index: usize,
+    active: bool,
+    // a requested transfer waiting for its start up delay
+    pending: Option<usize>,
+    // the last byte the dma put on the bus
+    current: u8,
+}
+impl Dma {
+    fn request(&mut self, high: u8) {
+        let mut source = (high as usize) << 8;
+        // anything above 0xDFFF is just echo ram
+        if source >= 0xE000 {
+            source -= 0x2000;
         }
-        tile_data.try_into().unwrap()
+        // a transfer that is already running keeps going until the new one starts
+        self.pending = Some(source);
+    }
+
+    /// whether the dma stops the cpu from accessing an address
+    fn blocks(&self, address: usize) -> bool {
+        self.active && address < 0xFF00
+    }
+
+    /// vram is on its own bus, everything else is on the external bus
+    fn shares_bus(&self, address: usize) -> bool {
+        is_within_vram(address) == is_within_vram(self.source)
     }
 }
 
-fn process_dma(mem: &mut Memory, address: u8) {
-    let real_address = (address as usize) << 8;
-    for i in 0..=0x9F {
-        mem.mem[0xFE00+i] = mem.mem[real_address+i];
+pub fn update_dma(memory: &mut Memory, cycles: u8) {
+    for _ in 0..cycles/4 {
+        if memory.dma.active {
+            let byte = memory.unchecked_read((memory.dma.source + memory.dma.index) as u16);
+            memory.oam[memory.dma.index] = byte;
+            memory.dma.current = byte;
+            memory.dma.index += 1;
+            memory.dma.active = memory.dma.index != 0xA0;
+        }
+        // this cycle was the start up delay, so the transfer begins (or restarts)
+        if let Some(source) = memory.dma.pending.take() {
+            memory.dma.source = source;
+            memory.dma.index = 0;
+            memory.dma.active = true;
+        }
     }
 }
 
 fn is_within_oam(index: usize) -> bool {
     index >= 0xFE00 && index <= 0xFE9F
 }
+fn is_within_unusable(index: usize) -> bool {
+    (0xFEA0..=0xFEFF).contains(&index)
+}
+/// the registers the ppu reads while drawing, it writes ly and stat itself
+fn is_ppu_register(address: u16) -> bool {
+    matches!(address, 0xFF40 | 0xFF42 | 0xFF43 | 0xFF47..=0xFF4B)
+}
 fn is_within_vram(index: usize) -> bool {
     index >= 0x8000 && index <= 0x9FFF
 }
-fn is_within_rom(index: usize) -> bool {
-    index <= 0x7FFF
-}
-fn is_within_ram(index: usize) -> bool {
-    index >= 0xA000 && index <= 0xBFFF
+
+pub fn update_cartridge(memory: &mut Memory, cycles: u8) {
+    memory.mbc.tick(cycles);
 }
 
 pub fn update_timer(memory: &mut Memory, cycles: u8) {
     use TimerRegisters::*;
-    let tac = memory.mem[TAC as usize];
+    let tac = memory.io.get(TAC as u16);
 
     let timer_enable = tac & 0b0000_0100 != 0;
     if !timer_enable {
@@ -209,14 +579,15 @@ pub fn update_timer(memory: &mut Memory, cycles: u8) {
 
         let anded_result = (memory.div & 1<<bit_position)!=0;
         if prev_edge && !anded_result {            
-            let tima = memory.mem[TIMA as usize];
+            let tima = memory.io.get(TIMA as u16);
             let (new_tima, overflow) = tima.overflowing_add(1);
-            memory.mem[TIMA as usize] = new_tima;
+            memory.io.set(TIMA as u16, new_tima);
 
             if overflow {
                 // timer gets reset to tma
-                memory.mem[TIMA as usize] = memory.mem[TMA as usize];
-                memory.mem[INTERRUPT_F_ADDRESS as usize] |= 0x04;
+                memory.io.set(TIMA as u16, memory.io.get(TMA as u16));
+                let interrupt_flag = memory.io.get(INTERRUPT_F_ADDRESS);
+                memory.io.set(INTERRUPT_F_ADDRESS, interrupt_flag | 0x04);
             }
         }
         prev_edge = anded_result;
@@ -0,0 +1,110 @@
+use clap::ValueEnum;
+use crate::cpu::{Flag, Registers};
+use crate::opcodes::inc;
+
+/// the different gameboys which can be emulated. They all run the same games
+/// but the boot roms leave them in slightly different states which games use
+/// to figure out what they are running on.
+/// the values are from https://gbdev.io/pandocs/Power_Up_Sequence.html
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Model {
+    Dmg0,
+    Dmg,
+    Mgb,
+    Sgb,
+    Sgb2,
+    Cgb,
+    Agb,
+}
+impl Model {
+    /// the colour models, these behave differently in a few places
+    pub fn is_cgb(&self) -> bool {
+        matches!(self, Model::Cgb | Model::Agb)
+    }
+
+    /// the cpu registers right as the boot rom jumps to 0x100
+    pub fn registers(&self, rom: &[u8]) -> Registers {
+        let header_checksum = rom[0x14D];
+        // the dmg boot rom leaves the half carry and carry set from the checksum
+        let dmg_flags = if header_checksum == 0 { 0x80 } else { 0xB0 };
+
+        let (a, f, b, c, d, e, h, l) = match self {
+            Model::Dmg0 => (0x01, 0x00, 0xFF, 0x13, 0x00, 0xC1, 0x84, 0x03),
+            Model::Dmg => (0x01, dmg_flags, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D),
+            Model::Mgb => (0xFF, dmg_flags, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D),
+            Model::Sgb => (0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60),
+            Model::Sgb2 => (0xFF, 0x00, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60),
+            Model::Cgb | Model::Agb => cgb_registers(rom),
+        };
+        let mut regs = Registers { a, f: Flag::from_u8(f), b, c, d, e, h, l, pc: 0x100, sp: 0xFFFE };
+
+        // the agb boot rom ends with an extra `INC B`
+        if *self == Model::Agb {
+            inc(&mut regs.b, &mut regs.f);
+        }
+        regs
+    }
+
+    /// the io registers the boot rom leaves behind, anything not in
+    /// here is left at 0
+    pub fn io_registers(&self) -> Vec<(u16, u8)> {
+        let mut registers = vec![
+            (0xFF00, 0xCF), // P1
+            (0xFF02, if self.is_cgb() { 0x7F } else { 0x7E }), // SC
+            (0xFF07, 0xF8), // TAC
+            (0xFF0F, 0xE1), // IF
+            (0xFF10, 0x80), (0xFF11, 0xBF), (0xFF12, 0xF3), (0xFF13, 0xFF), (0xFF14, 0xBF),
+            (0xFF16, 0x3F), (0xFF18, 0xFF), (0xFF19, 0xBF),
+            (0xFF1A, 0x7F), (0xFF1B, 0xFF), (0xFF1C, 0x9F), (0xFF1D, 0xFF), (0xFF1E, 0xBF),
+            (0xFF20, 0xFF), (0xFF23, 0xBF),
+            (0xFF24, 0x77), (0xFF25, 0xF3),
+            // the super gameboys hand the sound over to the snes
+            (0xFF26, if matches!(self, Model::Sgb | Model::Sgb2) { 0xF0 } else { 0xF1 }),
+            (0xFF40, 0x91), // LCDC
+            (0xFF46, if self.is_cgb() { 0x00 } else { 0xFF }), // DMA
+            (0xFF47, 0xFC), // BGP
+        ];
+        let (ly, _) = self.ppu_position();
+        // LY already reads as 0 for nearly all of line 153
+        registers.push((0xFF44, if ly == 153 { 0 } else { ly }));
+        registers
+    }
+
+    /// the full 16 bit divider, only the upper byte can be seen in DIV.
+    /// the super gameboys depend on the snes so these are just the common values
+    pub fn div(&self) -> u16 {
+        match self {
+            Model::Dmg0 => 0x1800,
+            Model::Dmg | Model::Mgb => 0xABCC,
+            Model::Sgb | Model::Sgb2 => 0xD85C,
+            Model::Cgb | Model::Agb => 0x267C,
+        }
+    }
+
+    /// the line and the dot in that line the ppu is at
+    pub fn ppu_position(&self) -> (u8, usize) {
+        match self {
+            Model::Dmg0 => (145, 0),
+            Model::Dmg | Model::Mgb | Model::Sgb | Model::Sgb2 => (153, 400),
+            Model::Cgb | Model::Agb => (144, 372),
+        }
+    }
+}
+
+/// a dmg game on a colour gameboy gets different registers depending on
+/// whether it is a colour game or not. Only nintendo's own games get the
+/// title checksum put into B (used to pick a colour palette)
+fn cgb_registers(rom: &[u8]) -> (u8, u8, u8, u8, u8, u8, u8, u8) {
+    if rom[0x143] & 0x80 != 0 {
+        return (0x11, 0x80, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D);
+    }
+
+    let nintendo = rom[0x14B] == 0x01 || (rom[0x14B] == 0x33 && &rom[0x144..0x146] == b"01");
+    let b = if nintendo {
+        rom[0x134..0x144].iter().fold(0_u8, |sum, byte| sum.wrapping_add(*byte))
+    } else {
+        0x00
+    };
+    let (h, l) = if b == 0x43 || b == 0x58 { (0x99, 0x1A) } else { (0x00, 0x7C) };
+    (0x11, 0x80, b, 0x00, 0x00, 0x08, h, l)
+}
@@ -3,7 +3,9 @@
 
 use crate::Cpu;
 use crate::Memory;
+use crate::memory::OamBug;
 use crate::cpu::Flag;
+use crate::util::little_endian_combine;
 
 //https://robdor.com/2016/08/10/gameboy-emulator-half-carry-flag/ goddamn is that smart
 fn half_carry_add(a: u8, b: u8) -> bool {
@@ -202,6 +204,18 @@ pub fn call(cpu: &mut Cpu, cc: bool, memory: &mut Memory) -> u8 {
     memory.write_word(cpu.regs.sp, fallback_address);
     return 24;
 }
+/// pops a word off of the stack. The stack pointer moving around
+/// inside of oam is one of the ways to trigger the oam bug
+pub fn pop(cpu: &mut Cpu, memory: &mut Memory) -> u16 {
+    memory.oam_bug(cpu.regs.sp, OamBug::ReadIncrease);
+    let low = memory.read(cpu.regs.sp);
+    memory.oam_bug(cpu.regs.sp.wrapping_add(1), OamBug::ReadIncrease);
+    let high = memory.read(cpu.regs.sp.wrapping_add(1));
+    memory.oam_bug(cpu.regs.sp.wrapping_add(2), OamBug::Write);
+
+    cpu.regs.sp = cpu.regs.sp.wrapping_add(2);
+    little_endian_combine(low, high)
+}
 pub fn rst(cpu: &mut Cpu, memory: &mut Memory, new: u16) {
     let fallback_address = cpu.regs.pc();
     cpu.regs.sp -= 2;
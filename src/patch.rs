@@ -0,0 +1,320 @@
+/// the patch formats romhacks and translations get shared as. The
+/// patch is applied in memory so the original rom is left alone
+pub const PATCH_EXTENSIONS: [&str; 3] = ["ips", "bps", "ups"];
+
+/// works out what kind of patch it is from the magic at the start
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    match patch {
+        _ if patch.starts_with(b"PATCH") => apply_ips(rom, patch),
+        _ if patch.starts_with(b"BPS1") => apply_bps(rom, patch),
+        _ if patch.starts_with(b"UPS1") => apply_ups(rom, patch),
+        _ => Err("not an ips, bps or ups patch".to_string()),
+    }
+}
+
+/// reads through the patch, erroring instead of panicking when it runs out
+struct PatchReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+impl<'a> PatchReader<'a> {
+    fn new(data: &'a [u8], position: usize) -> Self {
+        Self { data, position }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let bytes = self.data.get(self.position..self.position + length)
+            .ok_or("the patch ends too early")?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn big_endian(&mut self, length: usize) -> Result<usize, String> {
+        Ok(self.bytes(length)?.iter().fold(0, |value, byte| value << 8 | *byte as usize))
+    }
+
+    /// bps and ups store numbers 7 bits at a time, with the top bit marking the last byte
+    fn number(&mut self) -> Result<usize, String> {
+        let mut value = 0;
+        let mut shift = 1;
+        loop {
+            let byte = self.byte()? as usize;
+            value += (byte & 0x7F) * shift;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift <<= 7;
+            value += shift;
+        }
+    }
+}
+
+/// a list of records which each overwrite part of the rom, some of them
+/// fill a run with one byte instead. Can also cut the rom short at the end
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let mut target = rom.to_vec();
+    let mut reader = PatchReader::new(patch, 5);
+    loop {
+        if reader.bytes(3)? == b"EOF" {
+            break;
+        }
+        reader.position -= 3;
+        let offset = reader.big_endian(3)?;
+        let size = reader.big_endian(2)?;
+
+        let data = match size {
+            0 => {
+                let length = reader.big_endian(2)?;
+                vec![reader.byte()?; length]
+            }
+            _ => reader.bytes(size)?.to_vec(),
+        };
+        if target.len() < offset + data.len() {
+            target.resize(offset + data.len(), 0);
+        }
+        target[offset..offset + data.len()].copy_from_slice(&data);
+    }
+
+    if let Ok(length) = reader.big_endian(3) {
+        target.truncate(length);
+    }
+    Ok(target)
+}
+
+/// the last 12 bytes of bps and ups patches are the crc32s of the
+/// original rom, the patched rom and the rest of the patch
+fn checksums(rom: &[u8], patch: &[u8]) -> Result<(u32, u32), String> {
+    if patch.len() < 12 {
+        return Err("the patch ends too early".to_string());
+    }
+    let footer = &patch[patch.len() - 12..];
+    let crc = |i: usize| u32::from_le_bytes(footer[i..i + 4].try_into().unwrap());
+    if crc32(&patch[..patch.len() - 4]) != crc(8) {
+        return Err("the patch is corrupted".to_string());
+    }
+    if crc32(rom) != crc(0) {
+        return Err("the patch is not for this rom".to_string());
+    }
+    Ok((crc(0), crc(4)))
+}
+
+fn check_target(target: &[u8], expected: u32) -> Result<(), String> {
+    match crc32(target) == expected {
+        true => Ok(()),
+        false => Err("the patched rom does not match the patch's checksum".to_string()),
+    }
+}
+
+/// a list of actions which build the new rom from pieces of
+/// the old rom, the patch itself, or what has been built so far
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let (_, target_crc) = checksums(rom, patch)?;
+    let mut reader = PatchReader::new(&patch[..patch.len() - 12], 4);
+    let _source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata_size = reader.number()?;
+    reader.bytes(metadata_size)?;
+
+    let mut target: Vec<u8> = Vec::with_capacity(target_size);
+    let mut source_offset: isize = 0;
+    let mut target_offset: isize = 0;
+    // the copies move relative to where the last copy finished
+    let relative = |offset: &mut isize, data: usize| {
+        let distance = (data >> 1) as isize;
+        *offset += if data & 1 == 1 { -distance } else { distance };
+    };
+
+    while reader.position < reader.data.len() {
+        let data = reader.number()?;
+        let length = (data >> 2) + 1;
+        match data & 0b11 {
+            // source read, the same bytes as the original rom
+            0 => {
+                let start = target.len();
+                let bytes = rom.get(start..start + length).ok_or("the patch reads past the end of the rom")?;
+                target.extend_from_slice(bytes);
+            }
+            // target read, the bytes come from the patch
+            1 => target.extend_from_slice(reader.bytes(length)?),
+            // source copy, bytes from anywhere in the original rom
+            2 => {
+                relative(&mut source_offset, reader.number()?);
+                let start = source_offset as usize;
+                let bytes = rom.get(start..start + length).ok_or("the patch reads past the end of the rom")?;
+                target.extend_from_slice(bytes);
+                source_offset += length as isize;
+            }
+            // target copy, this can overlap what is being written so it goes a byte at a time
+            _ => {
+                relative(&mut target_offset, reader.number()?);
+                for _ in 0..length {
+                    let byte = *target.get(target_offset as usize).ok_or("the patch copies from past the end")?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    check_target(&target, target_crc)?;
+    Ok(target)
+}
+
+/// the difference between the roms xored together, with runs
+/// of unchanged bytes skipped over
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let (_, target_crc) = checksums(rom, patch)?;
+    let mut reader = PatchReader::new(&patch[..patch.len() - 12], 4);
+    let _source_size = reader.number()?;
+    let target_size = reader.number()?;
+
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0);
+    let mut position = 0;
+    while reader.position < reader.data.len() {
+        position += reader.number()?;
+        loop {
+            let byte = reader.byte()?;
+            if let Some(target_byte) = target.get_mut(position) {
+                *target_byte ^= byte;
+            }
+            position += 1;
+            if byte == 0 {
+                break;
+            }
+        }
+    }
+
+    check_target(&target, target_crc)?;
+    Ok(target)
+}
+
+/// the same crc32 zip files use
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the opposite of PatchReader::number
+    fn number(mut value: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let low = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(low | 0x80);
+                return bytes;
+            }
+            bytes.push(low);
+            value -= 1;
+        }
+    }
+
+    /// puts the crc32s of the rom, the patched rom and the patch on the end
+    fn with_checksums(mut patch: Vec<u8>, rom: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend(crc32(rom).to_le_bytes());
+        patch.extend(crc32(target).to_le_bytes());
+        patch.extend(crc32(&patch).to_le_bytes());
+        patch
+    }
+
+    fn bps_patch(rom: &[u8], target: &[u8]) -> Vec<u8> {
+        let mut patch = b"BPS1".to_vec();
+        patch.extend(number(rom.len()));
+        patch.extend(number(target.len()));
+        patch.extend(number(0));
+        // the first two bytes read from the rom, then two from the patch
+        patch.extend(number((2 - 1) << 2));
+        patch.extend(number((2 - 1) << 2 | 1));
+        patch.extend(&target[2..4]);
+        with_checksums(patch, rom, target)
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn ips_records_and_rle() {
+        let rom = [0; 8];
+        let mut patch = b"PATCH".to_vec();
+        // 2 bytes at 0x000001
+        patch.extend([0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]);
+        // 3 bytes of 0x55 at 0x000004
+        patch.extend([0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x55]);
+        patch.extend(b"EOF");
+        let patched = apply_patch(&rom, &patch).unwrap();
+        assert_eq!(patched, [0x00, 0xAA, 0xBB, 0x00, 0x55, 0x55, 0x55, 0x00]);
+    }
+
+    #[test]
+    fn ips_rle_past_the_end_grows_the_rom() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend([0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x04, 0x11]);
+        patch.extend(b"EOF");
+        assert_eq!(apply_patch(&[1, 2], &patch).unwrap(), [1, 2, 0x11, 0x11, 0x11, 0x11]);
+    }
+
+    #[test]
+    fn ips_truncated_record() {
+        let mut patch = b"PATCH".to_vec();
+        patch.extend([0x00, 0x00, 0x01, 0x00, 0x04, 0xAA]);
+        assert!(apply_patch(&[0; 4], &patch).is_err());
+    }
+
+    #[test]
+    fn bps_source_and_target_reads() {
+        let rom = [1, 2, 3, 4];
+        let target = [1, 2, 9, 9];
+        assert_eq!(apply_patch(&rom, &bps_patch(&rom, &target)).unwrap(), target);
+    }
+
+    #[test]
+    fn bps_bad_patch_crc() {
+        let rom = [1, 2, 3, 4];
+        let mut patch = bps_patch(&rom, &[1, 2, 9, 9]);
+        let last = patch.len() - 1;
+        patch[last] ^= 0xFF;
+        assert_eq!(apply_patch(&rom, &patch), Err("the patch is corrupted".to_string()));
+    }
+
+    #[test]
+    fn bps_wrong_rom() {
+        let patch = bps_patch(&[1, 2, 3, 4], &[1, 2, 9, 9]);
+        assert_eq!(apply_patch(&[1, 2, 3, 5], &patch), Err("the patch is not for this rom".to_string()));
+    }
+
+    #[test]
+    fn ups_xor_hunk() {
+        let rom = [1, 2, 3, 4];
+        let target = [1, 2, 9, 4];
+        let mut patch = b"UPS1".to_vec();
+        patch.extend(number(rom.len()));
+        patch.extend(number(target.len()));
+        patch.extend(number(2));
+        patch.extend([3 ^ 9, 0]);
+        let patch = with_checksums(patch, &rom, &target);
+        assert_eq!(apply_patch(&rom, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn unknown_patch_format() {
+        assert!(apply_patch(&[0; 4], b"NOPE").is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// how many T-cycles make up one second
+const CYCLES_PER_SECOND: usize = 4_194_304;
+
+/// the real time clock found in some mbc3 cartridges. It keeps counting in
+/// emulated time while the game runs and catches up to the real time
+/// that passed whenever a save gets loaded
+pub struct Rtc {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    // only 9 bits
+    days: u16,
+    halted: bool,
+    carry: bool,
+
+    // the values the game actually sees, only updated when latching
+    latched: [u8; 5],
+    // a latch happens when 0 then 1 gets written
+    latch_primed: bool,
+
+    cycles: usize,
+}
+impl Rtc {
+    pub fn new() -> Self {
+        Self {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days: 0,
+            halted: false,
+            carry: false,
+            latched: [0; 5],
+            latch_primed: false,
+            cycles: 0,
+        }
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        if self.halted {
+            return;
+        }
+        self.cycles += cycles as usize;
+        while self.cycles >= CYCLES_PER_SECOND {
+            self.cycles -= CYCLES_PER_SECOND;
+            self.tick_second();
+        }
+    }
+
+    /// the counters only carry over when they hit their limit exactly. Out of
+    /// range values the game wrote keep counting until their bits overflow
+    fn tick_second(&mut self) {
+        self.seconds = (self.seconds + 1) & 0x3F;
+        if self.seconds != 60 {
+            return;
+        }
+        self.seconds = 0;
+        self.minutes = (self.minutes + 1) & 0x3F;
+        if self.minutes != 60 {
+            return;
+        }
+        self.minutes = 0;
+        self.hours = (self.hours + 1) & 0x1F;
+        if self.hours != 24 {
+            return;
+        }
+        self.hours = 0;
+        self.days += 1;
+        if self.days == 0x200 {
+            self.days = 0;
+            self.carry = true;
+        }
+    }
+
+    /// moves the clock forward a lot of seconds at once, without
+    /// having to go through all of them one at a time
+    fn advance(&mut self, mut seconds: u64) {
+        if self.halted {
+            return;
+        }
+        while seconds > 0 && (self.seconds >= 60 || self.minutes >= 60 || self.hours >= 24) {
+            self.tick_second();
+            seconds -= 1;
+        }
+
+        let total = self.seconds as u64
+            + self.minutes as u64 * 60
+            + self.hours as u64 * 3600
+            + self.days as u64 * 86400
+            + seconds;
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / 3600 % 24) as u8;
+        let days = total / 86400;
+        if days >= 0x200 {
+            self.carry = true;
+        }
+        self.days = (days % 0x200) as u16;
+    }
+
+    fn day_high(&self) -> u8 {
+        (self.days >> 8) as u8 | (self.halted as u8) << 6 | (self.carry as u8) << 7
+    }
+
+    pub fn write_latch(&mut self, data: u8) {
+        if self.latch_primed && data == 1 {
+            self.latched = [self.seconds, self.minutes, self.hours, self.days as u8, self.day_high()];
+        }
+        self.latch_primed = data == 0;
+    }
+
+    /// the registers are selected with 0x08-0x0C, just like ram banks
+    pub fn read(&self, register: usize) -> u8 {
+        self.latched[register - 0x08]
+    }
+
+    pub fn write(&mut self, register: usize, data: u8) {
+        match register {
+            0x08 => {
+                self.seconds = data & 0x3F;
+                // writing the seconds resets the part of the second that has passed
+                self.cycles = 0;
+            }
+            0x09 => self.minutes = data & 0x3F,
+            0x0A => self.hours = data & 0x1F,
+            0x0B => self.days = (self.days & 0x100) | data as u16,
+            0x0C => {
+                self.days = (self.days & 0xFF) | ((data as u16 & 1) << 8);
+                self.halted = data & 0x40 != 0;
+                self.carry = data & 0x80 != 0;
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// the 5 registers then the 5 latched registers as 32 bit little endian
+    /// values, the same layout other emulators put after the save ram
+    pub fn save(&self) -> Vec<u8> {
+        let registers = [self.seconds, self.minutes, self.hours, self.days as u8, self.day_high()];
+        let mut data = Vec::new();
+        for register in registers.iter().chain(self.latched.iter()) {
+            data.extend_from_slice(&(*register as u32).to_le_bytes());
+        }
+        data
+    }
+
+    /// loads the clock and then adds on however long it has been since the save
+    pub fn load(&mut self, data: &[u8], elapsed: u64) {
+        if data.len() < CLOCK_SIZE {
+            return;
+        }
+        let value = |i: usize| u32::from_le_bytes(data[i*4..i*4+4].try_into().unwrap()) as u8;
+
+        self.write(0x08, value(0));
+        self.write(0x09, value(1));
+        self.write(0x0A, value(2));
+        self.write(0x0B, value(3));
+        self.write(0x0C, value(4));
+        for i in 0..5 {
+            self.latched[i] = value(5 + i);
+        }
+        self.advance(elapsed);
+    }
+}
+
+/// how many bytes the mbc3 clock takes up in a save
+pub const CLOCK_SIZE: usize = 40;
+
+/// how many bytes the timestamp on the end of a save with a clock takes up
+pub const TIMESTAMP_SIZE: usize = 8;
+
+/// saves with a clock in them are the ram, then the clock, then a 64 bit
+/// little endian unix timestamp so the clock can catch up when it gets loaded
+pub fn save_with_clock(ram: &[u8], clock: &[u8]) -> Vec<u8> {
+    let mut data = ram.to_vec();
+    data.extend_from_slice(clock);
+    data.extend_from_slice(&unix_time().to_le_bytes());
+    data
+}
+
+/// the other half of save_with_clock, gives back the clock and how many seconds
+/// went by since it was saved. Some emulators only store a 32 bit timestamp
+pub fn load_clock(data: &[u8], ram_size: usize, clock_size: usize) -> Option<(&[u8], u64)> {
+    let clock = data.get(ram_size..ram_size + clock_size)?;
+    let timestamp = &data[ram_size + clock_size..];
+    let timestamp = match timestamp.len() {
+        8.. => u64::from_le_bytes(timestamp[..8].try_into().unwrap()),
+        4.. => u32::from_le_bytes(timestamp[..4].try_into().unwrap()) as u64,
+        _ => return None,
+    };
+    Some((clock, unix_time().saturating_sub(timestamp)))
+}
+
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latched(rtc: &mut Rtc) -> [u8; 5] {
+        rtc.write_latch(0);
+        rtc.write_latch(1);
+        [0x08, 0x09, 0x0A, 0x0B, 0x0C].map(|register| rtc.read(register))
+    }
+
+    #[test]
+    fn catches_up_after_load() {
+        let mut rtc = Rtc::new();
+        rtc.write(0x08, 59);
+        rtc.write(0x09, 59);
+        rtc.write(0x0A, 23);
+        rtc.write(0x0B, 0xFF);
+        rtc.write(0x0C, 0x01);
+        let clock = rtc.save();
+
+        // one second takes it past day 511, which sets the carry
+        let mut loaded = Rtc::new();
+        loaded.load(&clock, 1 + 3600 + 60);
+        assert_eq!(latched(&mut loaded), [0, 1, 1, 0, 0x80]);
+    }
+
+    #[test]
+    fn halted_clock_doesnt_catch_up() {
+        let mut rtc = Rtc::new();
+        rtc.write(0x09, 30);
+        rtc.write(0x0C, 0x40);
+
+        let mut loaded = Rtc::new();
+        loaded.load(&rtc.save(), 86400);
+        assert_eq!(latched(&mut loaded), [0, 30, 0, 0, 0x40]);
+    }
+
+    #[test]
+    fn time_since_the_save() {
+        let ram = [0xAA; 0x10];
+        let save = save_with_clock(&ram, &[0x55; CLOCK_SIZE]);
+        assert_eq!(save.len(), ram.len() + CLOCK_SIZE + TIMESTAMP_SIZE);
+
+        // an hour ago, with the shorter timestamp some emulators write
+        let mut old = save[..ram.len() + CLOCK_SIZE].to_vec();
+        old.extend((unix_time() as u32 - 3600).to_le_bytes());
+        let (clock, elapsed) = load_clock(&old, ram.len(), CLOCK_SIZE).unwrap();
+        assert_eq!(clock, &[0x55; CLOCK_SIZE]);
+        assert!((3600..3602).contains(&elapsed));
+
+        assert!(load_clock(&save[..ram.len() + CLOCK_SIZE], ram.len(), CLOCK_SIZE).is_none());
+    }
+}
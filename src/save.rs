@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+use crate::memory::Memory;
+use crate::rtc::TIMESTAMP_SIZE;
+
+/// how many frames go by between each time the save gets written, about 10 seconds
+pub const SAVE_INTERVAL: u64 = 600;
+
+/// the .sav file next to the rom which battery backed ram gets kept in
+pub struct SaveFile {
+    path: PathBuf,
+    // stops the same save from being written over and over again
+    last_written: Option<Vec<u8>>,
+}
+impl SaveFile {
+    pub fn new(rom_path: &Path) -> Self {
+        Self {
+            path: rom_path.with_extension("sav"),
+            last_written: None,
+        }
+    }
+
+    pub fn load(&mut self, memory: &mut Memory) {
+        if let Ok(save) = std::fs::read(&self.path) {
+            memory.load_ram(&save);
+            self.last_written = Some(save);
+        }
+    }
+
+    /// writes the save, but only when it has changed since last time. The
+    /// timestamp on the end of saves with a clock changes every time so it
+    /// doesnt count, otherwise the file would get rewritten every interval
+    pub fn write(&mut self, memory: &Memory) {
+        let Some(data) = memory.save_ram() else {
+            return;
+        };
+        let timestamp = match memory.save_has_timestamp() {
+            true => TIMESTAMP_SIZE,
+            false => 0,
+        };
+        let contents = |save: &[u8]| save.len().saturating_sub(timestamp);
+        let unchanged = self.last_written.as_ref().is_some_and(|last| {
+            last[..contents(last)] == data[..contents(&data)]
+        });
+        if unchanged {
+            return;
+        }
+        match std::fs::write(&self.path, &data) {
+            Ok(_) => self.last_written = Some(data),
+            Err(e) => eprintln!("couldn't write the save file => {e:?}"),
+        }
+    }
+}
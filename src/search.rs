@@ -0,0 +1,130 @@
+use std::fmt;
+use crate::memory::Memory;
+
+/// the places a game would keep things like lives, wram and hram.
+/// cartridge ram gets searched separately so every bank is included
+const SEARCH_REGIONS: [(u16, u16); 2] = [(0xC000, 0xDFFF), (0xFF80, 0xFFFE)];
+const CARTRIDGE_BANK_SIZE: usize = 0x2000;
+/// gameshark codes only have 4 bits for the bank, so a cheat
+/// couldnt be made from anything found past this
+const CHEAT_BANKS: usize = 0x10;
+
+/// where a value is kept, cartridge ram also needs the bank
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Location {
+    Cartridge { bank: u8, address: u16 },
+    Ram(u16),
+}
+impl Location {
+    pub fn next(&self) -> Self {
+        match *self {
+            Location::Cartridge { bank, address } => Location::Cartridge { bank, address: address.wrapping_add(1) },
+            Location::Ram(address) => Location::Ram(address.wrapping_add(1)),
+        }
+    }
+
+    /// cartridge ram is read straight out of the mbc, so it doesnt matter
+    /// whether the game has it enabled or which bank is mapped in
+    pub fn read(&self, memory: &Memory) -> u8 {
+        match *self {
+            Location::Cartridge { bank, address } => {
+                let index = bank as usize * CARTRIDGE_BANK_SIZE + (address - 0xA000) as usize;
+                memory.cartridge_ram().get(index).copied().unwrap_or(0xFF)
+            }
+            Location::Ram(address) => memory.unchecked_read(address),
+        }
+    }
+}
+/// the same way watches and cheats take them, `bank:address`
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Cartridge { bank, address } => write!(f, "{bank:X}:{address:04X}"),
+            Location::Ram(address) => write!(f, "{address:04X}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Width {
+    Byte,
+    Word,
+}
+impl Width {
+    /// 16 bit values are little endian like everything else on the gameboy
+    pub fn read(&self, memory: &Memory, location: Location) -> u16 {
+        match self {
+            Width::Byte => location.read(memory) as u16,
+            Width::Word => {
+                let low = location.read(memory) as u16;
+                let high = location.next().read(memory) as u16;
+                high << 8 | low
+            }
+        }
+    }
+}
+
+/// how the values now get compared to the ones from the last snapshot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    Value(u16),
+}
+impl Filter {
+    fn keeps(&self, previous: u16, current: u16) -> bool {
+        match self {
+            Filter::Equal => current == previous,
+            Filter::Changed => current != previous,
+            Filter::Increased => current > previous,
+            Filter::Decreased => current < previous,
+            Filter::Value(value) => current == *value,
+        }
+    }
+}
+
+/// narrows down where a value is kept by taking snapshots of the ram and
+/// throwing away every address that doesnt change the way it should
+pub struct RamSearch {
+    width: Width,
+    // each place that could still be it, along with its value last snapshot
+    candidates: Vec<(Location, u16)>,
+}
+impl RamSearch {
+    /// starts a new search with every address as a candidate
+    pub fn new(memory: &Memory, width: Width) -> Self {
+        let last = |end: u16| if width == Width::Word { end - 1 } else { end };
+        let banks = memory.cartridge_ram().len().div_ceil(CARTRIDGE_BANK_SIZE).min(CHEAT_BANKS);
+        let bank_end = 0xA000 + memory.cartridge_ram().len().min(CARTRIDGE_BANK_SIZE) as u16 - 1;
+
+        let cartridge = (0..banks as u8).flat_map(|bank| {
+            (0xA000..=last(bank_end)).map(move |address| Location::Cartridge { bank, address })
+        });
+        let ram = SEARCH_REGIONS.iter()
+            .flat_map(|(start, end)| *start..=last(*end))
+            .map(Location::Ram);
+        let candidates = cartridge.chain(ram)
+            .map(|location| (location, width.read(memory, location)))
+            .collect();
+        Self { width, candidates }
+    }
+
+    pub fn filter(&mut self, memory: &Memory, filter: Filter) {
+        let width = self.width;
+        self.candidates.retain_mut(|(location, previous)| {
+            let current = width.read(memory, *location);
+            let keep = filter.keeps(*previous, current);
+            *previous = current;
+            keep
+        });
+    }
+
+    pub fn width(&self) -> Width {
+        self.width
+    }
+    pub fn candidates(&self) -> &[(Location, u16)] {
+        &self.candidates
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::{Path, PathBuf};
+use crate::memory::Memory;
+use crate::search::Location;
+
+/// how the bytes at a watched address get shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchType {
+    U8,
+    U16Le,
+    U16Be,
+    // each nybble is a decimal digit, used a lot for scores
+    Bcd,
+    Signed,
+}
+impl WatchType {
+    fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "u8" => Ok(WatchType::U8),
+            "u16" | "u16le" => Ok(WatchType::U16Le),
+            "u16be" => Ok(WatchType::U16Be),
+            "bcd" => Ok(WatchType::Bcd),
+            "i8" | "signed" => Ok(WatchType::Signed),
+            _ => Err(format!("{text} is not a type, it can be u8, u16le, u16be, bcd or signed")),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            WatchType::U8 => "u8",
+            WatchType::U16Le => "u16le",
+            WatchType::U16Be => "u16be",
+            WatchType::Bcd => "bcd",
+            WatchType::Signed => "signed",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Watch {
+    pub name: String,
+    pub address: u16,
+    pub kind: WatchType,
+    // the ram bank the value is in, wram only gets shown while that bank is mapped
+    pub bank: Option<u8>,
+}
+impl Watch {
+    /// parses `[bank:]address type name`, the same as it gets saved
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut words = text.split_whitespace();
+        let (Some(location), Some(kind)) = (words.next(), words.next()) else {
+            return Err("a watch needs an address and a type".to_string());
+        };
+        let (bank, address) = match location.split_once(':') {
+            Some((bank, address)) => {
+                let bank = u8::from_str_radix(bank, 16).map_err(|_| format!("{bank} is not a bank"))?;
+                (Some(bank), address)
+            }
+            None => (None, location),
+        };
+        let address = u16::from_str_radix(address.trim_start_matches("0x"), 16)
+            .map_err(|_| format!("{address} is not an address"))?;
+        let name = words.collect::<Vec<&str>>().join(" ");
+
+        Ok(Self { name, address, kind: WatchType::parse(kind)?, bank })
+    }
+
+    pub fn to_line(&self) -> String {
+        let location = match self.bank {
+            Some(bank) => format!("{bank:X}:{:04X}", self.address),
+            None => format!("{:04X}", self.address),
+        };
+        format!("{location} {} {}", self.kind.name(), self.name)
+    }
+
+    /// the value as text. Cartridge ram gets read from its bank whatever is
+    /// mapped in, anything else banked shows nothing while it isnt mapped
+    pub fn value(&self, memory: &Memory) -> Option<String> {
+        let location = match (self.bank, self.address) {
+            (Some(bank), 0xA000..=0xBFFF) => Location::Cartridge { bank, address: self.address },
+            (Some(bank), address) if !memory.bank_mapped(bank, address) => return None,
+            (_, address) => Location::Ram(address),
+        };
+        let byte = location.read(memory);
+        let next = || location.next().read(memory);
+        let value = match self.kind {
+            WatchType::U8 => byte.to_string(),
+            WatchType::U16Le => u16::from_le_bytes([byte, next()]).to_string(),
+            WatchType::U16Be => u16::from_be_bytes([byte, next()]).to_string(),
+            WatchType::Bcd => format!("{:X}{:X}", byte >> 4, byte & 0x0F),
+            WatchType::Signed => (byte as i8).to_string(),
+        };
+        Some(value)
+    }
+}
+
+/// the watches for a rom, kept next to it in a .wch file
+pub struct WatchList {
+    path: PathBuf,
+    watches: Vec<Watch>,
+}
+impl WatchList {
+    pub fn load(rom_path: &Path) -> Self {
+        let path = rom_path.with_extension("wch");
+        let mut watches = Vec::new();
+        if let Ok(file) = std::fs::read_to_string(&path) {
+            for line in file.lines().filter(|line| !line.trim().is_empty()) {
+                match Watch::parse(line) {
+                    Ok(watch) => watches.push(watch),
+                    Err(e) => eprintln!("skipping a watch in {}: {e}", path.display()),
+                }
+            }
+        }
+        Self { path, watches }
+    }
+
+    fn save(&self) {
+        let lines: Vec<String> = self.watches.iter().map(Watch::to_line).collect();
+        if let Err(e) = std::fs::write(&self.path, lines.join("\n") + "\n") {
+            eprintln!("could not save the watches to {}: {e}", self.path.display());
+        }
+    }
+
+    pub fn add(&mut self, watch: Watch) {
+        self.watches.push(watch);
+        self.save();
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Watch> {
+        if index >= self.watches.len() {
+            return None;
+        }
+        let watch = self.watches.remove(index);
+        self.save();
+        Some(watch)
+    }
+
+    pub fn watches(&self) -> &[Watch] {
+        &self.watches
+    }
+}